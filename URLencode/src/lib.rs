@@ -1,13 +1,12 @@
-use clap::error::Result;
-use clap::{builder, Args, Parser, Subcommand};
+#![allow(non_snake_case)]
+use clap::{ArgGroup, Args, Parser, Subcommand};
 use thiserror::Error;
-use std::error::Error;
-use std::fmt::{format, write};
 use std::fs::{self, File};
-use std::io::{BufReader, Read, Write, SeekFrom};
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::str;
-use std::string::FromUtf8Error;
+use std::sync::Arc;
 
 
 //ANCHOR - Clap section
@@ -26,11 +25,23 @@ pub enum Command {
     ///Decode from inputting URL
     #[clap(short_flag = 'd', about = "Decode input URL components")]
     Decode(Form2),
+    ///List the query parameters of inputting URL
+    #[clap(short_flag = 'q', about = "Parse the query string of input URLs into parameters")]
+    Query(Form3),
+    ///Normalize inputting URL
+    #[clap(short_flag = 'n', about = "Normalize input URLs (RFC 3986 section 6)")]
+    Normalize(Form4),
+    ///Guess the encoding of inputting text
+    #[clap(about = "Guess how input text is encoded, --recursive lists every layer")]
+    Detect(Form5),
 }
 
 #[derive(Debug, Args)]
+//one codec per run, --percent is the default and the only one --set and --keep work with
+#[clap(group(ArgGroup::new("codecs").multiple(false).args(["flg2", "flg5", "flg6", "flg7", "codec", "base32", "flg13", "base58", "base85", "entities", "flg16", "flg17", "header_param", "chain"])))]
+#[clap(group(ArgGroup::new("unpadded").multiple(true).args(["flg5", "flg6", "codec", "flg13", "base58", "base85", "entities", "flg16", "flg17", "header_param", "chain"])))]
 pub struct Form1 {
-    ///Input text, file or filepath
+    ///Input text, file or filepath, '-' reads stdin
     #[clap(required = true, value_name = "TEXT/PATH")]
     pub filetext: StringOrPath,
 
@@ -39,7 +50,7 @@ pub struct Form1 {
         short = 'p',
         long = "percent",
         value_name = "TO_PERCENT",
-        conflicts_with = "flg2"
+        conflicts_with = "codecs"
     )]
     pub flg1: bool,
 
@@ -54,16 +65,131 @@ pub struct Form1 {
     ///Getting result via terminal<DEFAULT>
     #[clap(long="toterminal",value_name="TO_TERMINAL",alias="tt",short='t')]
     pub flg4:bool,
+
+    ///Output format: terminal<DEFAULT>, html, plain, json, ndjson, csv, tsv, markdown
+    #[clap(long = "format", value_name = "FORMAT", conflicts_with_all = ["flg3", "flg4"])]
+    pub format: Option<FileLocate>,
+
+    ///Whole URL mode: encode each component of the URL with its own rules, an internationalized host becomes xn--
+    #[clap(short = 'u', long = "url", value_name = "WHOLE_URL")]
+    pub flg5: bool,
+
+    ///Percent-encode set: legacy<DEFAULT>, unreserved, alphanumeric, controls, fragment, query, special-query, path, userinfo, component, form, attr-char
+    #[clap(long = "set", value_name = "NAME", conflicts_with = "codecs")]
+    pub set: Option<EncodeSet>,
+
+    ///Characters to leave as they are on top of the chosen set
+    #[clap(long = "keep", value_name = "CHARS", conflicts_with = "codecs")]
+    pub keep: Option<String>,
+
+    ///application/x-www-form-urlencoded: space becomes '+' and '+' becomes %2B
+    #[clap(short = 'f', long = "form", value_name = "TO_FORM")]
+    pub flg6: bool,
+
+    ///Resolve every input as a reference against this URL before encoding
+    #[clap(long = "base", value_name = "URL")]
+    pub base: Option<String>,
+
+    ///Base64-Encoding with the URL-safe alphabet ('-' and '_')
+    #[clap(long = "base64url", value_name = "TO_BASE64URL")]
+    pub flg7: bool,
+
+    ///Base64 or Base32 encoding without '=' padding
+    #[clap(long = "no-pad", value_name = "NO_PAD", conflicts_with_all = ["flg1", "set", "keep", "unpadded"])]
+    pub flg8: bool,
+
+    ///Binary mode: encode the whole file as one payload of raw bytes
+    #[clap(long = "binary", value_name = "BINARY", conflicts_with_all = ["flg5", "base"])]
+    pub flg9: bool,
+
+    ///Treat the input as literal text even when a file with that name exists
+    #[clap(long = "text", value_name = "TEXT", conflicts_with = "flg11")]
+    pub flg10: bool,
+
+    ///Treat the input as a file path, it is an error when the file does not exist
+    #[clap(long = "file", value_name = "FILE")]
+    pub flg11: bool,
+
+    ///Write the result to this file, html goes to output.html when it is not given
+    #[clap(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    ///Refuse to overwrite a file that already exists
+    #[clap(long = "no-clobber", value_name = "NO_CLOBBER")]
+    pub flg12: bool,
+
+    ///Encode with this codec from the registry: percent, form, base64, base64url, base32, base32hex, crockford, hex, base58, base58flickr, base58check, ascii85, adobe85, rfc1924, z85, punycode, html, html-attr, quoted-printable, mime, header-param
+    #[clap(long = "codec", value_name = "NAME")]
+    pub codec: Option<String>,
+
+    ///Base32-Encoding, alphabet: standard<DEFAULT>, hex, crockford (never padded)
+    #[clap(long = "base32", value_name = "ALPHABET", num_args = 0..=1, require_equals = true, default_missing_value = "standard")]
+    pub base32: Option<Base32Alphabet>,
+
+    ///Hex (Base16) Encoding, lowercase unless --upper is given
+    #[clap(long = "hex", value_name = "TO_HEX")]
+    pub flg13: bool,
+
+    ///Uppercase hex digits
+    #[clap(long = "upper", value_name = "UPPER", requires = "flg13")]
+    pub flg14: bool,
+
+    ///Put this between the hex bytes: none<DEFAULT>, colon (':'), space (' '), escape ('\x' before every byte)
+    #[clap(long = "hex-sep", value_name = "SEP", requires = "flg13")]
+    pub separator: Option<HexSeparator>,
+
+    ///Base58-Encoding, alphabet: bitcoin<DEFAULT>, flickr
+    #[clap(long = "base58", value_name = "ALPHABET", num_args = 0..=1, require_equals = true, default_missing_value = "bitcoin")]
+    pub base58: Option<Base58Alphabet>,
+
+    ///Base58Check: append a 4-byte double-SHA-256 checksum
+    #[clap(long = "check", value_name = "CHECK", requires = "base58")]
+    pub flg15: bool,
+
+    ///Base58Check version byte put in front of the payload
+    #[clap(long = "version-byte", value_name = "N", requires = "flg15")]
+    pub version_byte: Option<u8>,
+
+    ///Base85-Encoding, variant: adobe<DEFAULT> ('<~ ~>'), ascii85 (btoa), rfc1924, z85
+    #[clap(long = "base85", value_name = "VARIANT", num_args = 0..=1, require_equals = true, default_missing_value = "adobe")]
+    pub base85: Option<Base85Variant>,
+
+    ///HTML character references, context: text<DEFAULT> escapes & < >, attribute escapes & " '
+    #[clap(long = "entities", value_name = "CONTEXT", num_args = 0..=1, require_equals = true, default_missing_value = "text")]
+    pub entities: Option<HtmlContext>,
+
+    ///Quoted-printable (RFC 2045) of the whole input with soft line breaks at 76 characters, with --binary CR and LF are escaped as =0D =0A
+    #[clap(long = "qp", value_name = "TO_QP", conflicts_with = "base")]
+    pub flg16: bool,
+
+    ///MIME encoded-words (RFC 2047) for a header, B or Q is picked by length and words are split at 75 characters
+    #[clap(long = "mime", value_name = "TO_MIME")]
+    pub flg17: bool,
+
+    ///HTTP header parameter (RFC 8187): an ASCII fallback NAME="..." and NAME*=UTF-8''... for the value, NAME is filename<DEFAULT>
+    #[clap(long = "header-param", value_name = "NAME", num_args = 0..=1, require_equals = true, default_missing_value = "filename")]
+    pub header_param: Option<String>,
+
+    ///Language tag of the header parameter, e.g. "en"
+    #[clap(long = "lang", value_name = "TAG", requires = "header_param")]
+    pub lang: Option<String>,
+
+    ///Run several codecs in order, e.g. "base64,percent" or "base64|percent", every stage is shown
+    #[clap(long = "chain", value_name = "NAMES")]
+    pub chain: Option<String>,
 }
 
 #[derive(Debug, Args)]
+//one codec per run, --percent is the default
+#[clap(group(ArgGroup::new("codecs").multiple(false).args(["flg2", "flg5", "flg6", "codec", "base32", "flg13", "base58", "base85", "flg17", "flg18", "flg19", "header_param", "flg16", "flg12", "chain"])))]
+#[clap(group(ArgGroup::new("unpadded").multiple(true).args(["flg5", "codec", "flg13", "base58", "base85", "flg17", "flg18", "flg19", "header_param", "flg16", "flg12", "chain"])))]
 pub struct Form2 {
-    ///Input text, file or filepath
+    ///Input text, file or filepath, '-' reads stdin
     #[clap(required = true, value_name = "TEXT/PATH")]
     pub filetext: StringOrPath,
 
     ///Percent-Decoding<DEFAULT>
-    #[clap(short = 'p', long = "percent", value_name = "FROM_PERCENT")]
+    #[clap(short = 'p', long = "percent", value_name = "FROM_PERCENT", conflicts_with = "codecs")]
     pub flg1: bool,
 
     ///Base64-Decoding
@@ -77,6 +203,258 @@ pub struct Form2 {
     ///Getting result via terminal<DEFAULT>
     #[clap(long="toterminal",value_name="TO_TERMINAL",alias="tt",short='t')]
     pub flg4:bool,
+
+    ///Output format: terminal<DEFAULT>, html, plain, json, ndjson, csv, tsv, markdown
+    #[clap(long = "format", value_name = "FORMAT", conflicts_with_all = ["flg3", "flg4"])]
+    pub format: Option<FileLocate>,
+
+    ///application/x-www-form-urlencoded: '+' turns back into a space
+    #[clap(short = 'f', long = "form", value_name = "FROM_FORM")]
+    pub flg5: bool,
+
+    ///Base64-Decoding of URL-safe input ('-' and '_'), the standard alphabet is accepted too
+    #[clap(long = "base64url", value_name = "FROM_BASE64URL")]
+    pub flg6: bool,
+
+    ///Base64-Decoding of input without '=' padding
+    #[clap(long = "no-pad", value_name = "NO_PAD", conflicts_with_all = ["flg1", "unpadded"])]
+    pub flg7: bool,
+
+    ///Binary mode: decode the whole file as one payload of raw bytes
+    #[clap(long = "binary", value_name = "BINARY")]
+    pub flg8: bool,
+
+    ///Write the result to this file, in binary mode the decoded bytes instead of printing them
+    #[clap(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    ///Treat the input as literal text even when a file with that name exists
+    #[clap(long = "text", value_name = "TEXT", conflicts_with = "flg10")]
+    pub flg9: bool,
+
+    ///Treat the input as a file path, it is an error when the file does not exist
+    #[clap(long = "file", value_name = "FILE")]
+    pub flg10: bool,
+
+    ///Refuse to overwrite a file that already exists
+    #[clap(long = "no-clobber", value_name = "NO_CLOBBER")]
+    pub flg11: bool,
+
+    ///Decode with this codec from the registry: percent, form, base64, base64url, base32, base32hex, crockford, hex, base58, base58flickr, base58check, ascii85, adobe85, rfc1924, z85, punycode, html, html-attr, quoted-printable, mime, header-param
+    #[clap(long = "codec", value_name = "NAME")]
+    pub codec: Option<String>,
+
+    ///Base32-Decoding, alphabet: standard<DEFAULT>, hex, crockford, padding is optional
+    #[clap(long = "base32", value_name = "ALPHABET", num_args = 0..=1, require_equals = true, default_missing_value = "standard")]
+    pub base32: Option<Base32Alphabet>,
+
+    ///Hex (Base16) Decoding, whitespace, ':' and '\x' between the bytes are skipped
+    #[clap(long = "hex", value_name = "FROM_HEX")]
+    pub flg13: bool,
+
+    ///Show the decoded bytes as an xxd-style dump with offsets and an ASCII gutter
+    #[clap(short = 'x', long = "hexdump", value_name = "HEXDUMP")]
+    pub flg14: bool,
+
+    ///Base58-Decoding, alphabet: bitcoin<DEFAULT>, flickr
+    #[clap(long = "base58", value_name = "ALPHABET", num_args = 0..=1, require_equals = true, default_missing_value = "bitcoin")]
+    pub base58: Option<Base58Alphabet>,
+
+    ///Base58Check: verify and remove the 4-byte double-SHA-256 checksum
+    #[clap(long = "check", value_name = "CHECK", requires = "base58")]
+    pub flg15: bool,
+
+    ///Base58Check version byte the payload has to start with, it is removed
+    #[clap(long = "version-byte", value_name = "N", requires = "flg15")]
+    pub version_byte: Option<u8>,
+
+    ///Base85-Decoding, variant: adobe<DEFAULT>, ascii85, rfc1924, z85, Ascii85 takes input with or without '<~ ~>'
+    #[clap(long = "base85", value_name = "VARIANT", num_args = 0..=1, require_equals = true, default_missing_value = "adobe")]
+    pub base85: Option<Base85Variant>,
+
+    ///HTML character references: named, decimal (&#47;) and hex (&#x2F;), unknown ones are kept
+    #[clap(long = "entities", value_name = "FROM_ENTITIES")]
+    pub flg17: bool,
+
+    ///Quoted-printable (RFC 2045) of the whole input, soft line breaks are joined and hard line breaks become CRLF
+    #[clap(long = "qp", value_name = "FROM_QP")]
+    pub flg18: bool,
+
+    ///MIME encoded-words (RFC 2047) in UTF-8, US-ASCII or ISO-8859-1, the text around them is kept
+    #[clap(long = "mime", value_name = "FROM_MIME")]
+    pub flg19: bool,
+
+    ///HTTP header parameter (RFC 8187): the value of NAME* or else NAME in a header like Content-Disposition, NAME is filename<DEFAULT>
+    #[clap(long = "header-param", value_name = "NAME", num_args = 0..=1, require_equals = true, default_missing_value = "filename")]
+    pub header_param: Option<String>,
+
+    ///Whole URL mode: decode each component of the URL and show an xn-- host in Unicode
+    #[clap(short = 'u', long = "url", value_name = "WHOLE_URL", conflicts_with_all = ["flg8", "flg14"])]
+    pub flg16: bool,
+
+    ///Keep applying the best guess of `detect` until the result stops changing, every layer is shown
+    #[clap(short = 'r', long = "recursive", value_name = "RECURSIVE", conflicts_with_all = ["flg8", "flg14"])]
+    pub flg12: bool,
+
+    ///Undo a chain of codecs named in encoding order, e.g. "base64,percent" decodes percent first, every stage is shown
+    #[clap(long = "chain", value_name = "NAMES")]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct Form3 {
+    ///Input URL, query string, file or filepath, '-' reads stdin
+    #[clap(required = true, value_name = "TEXT/PATH")]
+    pub filetext: StringOrPath,
+
+    ///Getting result via table html format
+    #[clap(long="tohtml",value_name="TO_HTML",conflicts_with= "flg4", alias="th", short='l')]
+    pub flg3:bool,
+
+    ///Getting result via terminal<DEFAULT>
+    #[clap(long="toterminal",value_name="TO_TERMINAL",alias="tt",short='t')]
+    pub flg4:bool,
+
+    ///Output format: terminal<DEFAULT>, html, plain, json, ndjson, csv, tsv, markdown
+    #[clap(long = "format", value_name = "FORMAT", conflicts_with_all = ["flg3", "flg4"])]
+    pub format: Option<FileLocate>,
+
+    ///Set a parameter, replacing every value the key already has
+    #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub set: Vec<(String, String)>,
+
+    ///Append a parameter, keeping the existing ones
+    #[clap(long = "add", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub add: Vec<(String, String)>,
+
+    ///Remove every parameter with this key
+    #[clap(long = "remove", value_name = "KEY")]
+    pub remove: Vec<String>,
+
+    ///Sort the parameters by key, repeated keys keep their order
+    #[clap(long = "sort", value_name = "SORT")]
+    pub flg5: bool,
+
+    ///Treat the input as literal text even when a file with that name exists
+    #[clap(long = "text", value_name = "TEXT", conflicts_with = "flg7")]
+    pub flg6: bool,
+
+    ///Treat the input as a file path, it is an error when the file does not exist
+    #[clap(long = "file", value_name = "FILE")]
+    pub flg7: bool,
+
+    ///Write the result to this file, html goes to output.html when it is not given
+    #[clap(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    ///Refuse to overwrite a file that already exists
+    #[clap(long = "no-clobber", value_name = "NO_CLOBBER")]
+    pub flg8: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Form4 {
+    ///Input URL, file or filepath, '-' reads stdin
+    #[clap(required = true, value_name = "TEXT/PATH")]
+    pub filetext: StringOrPath,
+
+    ///Getting result via table html format
+    #[clap(long="tohtml",value_name="TO_HTML",conflicts_with= "flg4", alias="th", short='l')]
+    pub flg3:bool,
+
+    ///Getting result via terminal<DEFAULT>
+    #[clap(long="toterminal",value_name="TO_TERMINAL",alias="tt",short='t')]
+    pub flg4:bool,
+
+    ///Output format: terminal<DEFAULT>, html, plain, json, ndjson, csv, tsv, markdown
+    #[clap(long = "format", value_name = "FORMAT", conflicts_with_all = ["flg3", "flg4"])]
+    pub format: Option<FileLocate>,
+
+    ///Keep the case of scheme and host
+    #[clap(long = "keep-case", value_name = "KEEP_CASE")]
+    pub flg5: bool,
+
+    ///Keep the case of hex digits in escapes
+    #[clap(long = "keep-hex-case", value_name = "KEEP_HEX_CASE")]
+    pub flg6: bool,
+
+    ///Keep percent-encoded unreserved characters encoded
+    #[clap(long = "keep-unreserved", value_name = "KEEP_UNRESERVED")]
+    pub flg7: bool,
+
+    ///Keep "." and ".." path segments
+    #[clap(long = "keep-dots", value_name = "KEEP_DOTS")]
+    pub flg8: bool,
+
+    ///Keep the port even when it is the scheme default
+    #[clap(long = "keep-port", value_name = "KEEP_PORT")]
+    pub flg9: bool,
+
+    ///Keep an empty path empty instead of "/"
+    #[clap(long = "keep-empty-path", value_name = "KEEP_EMPTY_PATH")]
+    pub flg10: bool,
+
+    ///Treat the input as literal text even when a file with that name exists
+    #[clap(long = "text", value_name = "TEXT", conflicts_with = "flg12")]
+    pub flg11: bool,
+
+    ///Treat the input as a file path, it is an error when the file does not exist
+    #[clap(long = "file", value_name = "FILE")]
+    pub flg12: bool,
+
+    ///Write the result to this file, html goes to output.html when it is not given
+    #[clap(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    ///Refuse to overwrite a file that already exists
+    #[clap(long = "no-clobber", value_name = "NO_CLOBBER")]
+    pub flg13: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Form5 {
+    ///Input text, file or filepath, '-' reads stdin
+    #[clap(required = true, value_name = "TEXT/PATH")]
+    pub filetext: StringOrPath,
+
+    ///Getting result via table html format
+    #[clap(long="tohtml",value_name="TO_HTML",conflicts_with= "flg4", alias="th", short='l')]
+    pub flg3:bool,
+
+    ///Getting result via terminal<DEFAULT>
+    #[clap(long="toterminal",value_name="TO_TERMINAL",alias="tt",short='t')]
+    pub flg4:bool,
+
+    ///Output format: terminal<DEFAULT>, html, plain, json, ndjson, csv, tsv, markdown
+    #[clap(long = "format", value_name = "FORMAT", conflicts_with_all = ["flg3", "flg4"])]
+    pub format: Option<FileLocate>,
+
+    ///Decode the best guess again and again until it is stable, and list the layers
+    #[clap(short = 'r', long = "recursive", value_name = "RECURSIVE")]
+    pub flg5: bool,
+
+    ///Treat the input as literal text even when a file with that name exists
+    #[clap(long = "text", value_name = "TEXT", conflicts_with = "flg7")]
+    pub flg6: bool,
+
+    ///Treat the input as a file path, it is an error when the file does not exist
+    #[clap(long = "file", value_name = "FILE")]
+    pub flg7: bool,
+
+    ///Write the result to this file, html goes to output.html when it is not given
+    #[clap(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    ///Refuse to overwrite a file that already exists
+    #[clap(long = "no-clobber", value_name = "NO_CLOBBER")]
+    pub flg8: bool,
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) => Ok((k.to_string(), v.to_string())),
+        None => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
 }
 
 //------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
@@ -84,516 +462,4507 @@ pub struct Form2 {
 
 //ANCHOR - Impl Linking Encode to clap
 impl Form1 {
-    pub fn linking(&self) -> Result<(FileContent,FileLocate), Box<dyn Error>> {
-        let fileloc = if self.flg3 {
-            FileLocate::Html
-        } else if self.flg4 {
-            FileLocate::Terminal
-        } else {
-            FileLocate::Terminal
-        };
+    //returns the inputs and the results as two columns
+    pub fn linking(&self, codecs: &CodecRegistry) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        let fileloc = filelocate(self.format, self.flg3);
+        let source = self.filetext.forced(self.flg10, self.flg11);
+        if let Some(spec) = &self.chain {
+            return self.linkchain(&source, fileloc, &codecs.chain(spec)?);
+        }
+        let codec = self.codec(codecs)?;
 
+        //quoted-printable text is one body too, its line breaks become hard line breaks
+        if self.flg9 || self.flg16 {
+            let bytes = source.read_bytes()?;
+            return Ok((FileContent::Single(source.label()),FileContent::Single(codec.encode(&bytes)),fileloc,Vec::new()));
+        }
+        linklines(&source, fileloc, self.output.is_some(), |i| self.process(codec.as_ref(), i))
+    }
 
-        match &self.filetext {
-            StringOrPath::String(inp) => {
-                // println!("{:?}",inp);
-                if self.flg1 {
-                    Ok((FileContent::Single(encoding_percent_component(&inp)),fileloc))
-                } else if self.flg2 {
-                    Ok((FileContent::Single(tobase64(&inp)), fileloc)) //wiil change when to base64 is finish
-                } else {
-                    Ok((FileContent::Single(encoding_percent_component(&inp)),fileloc)) //for default choice
-                }
-            }
-            StringOrPath::Path(path) => {
-                let filecontent = readfile(&path.clone().into_os_string().into_string().unwrap())?;
-                match filecontent {
-                    FileContent::Multiple(lines) => {
-                        if self.flg1 {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = encoding_percent_component(&i);
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        } else if self.flg2 {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = tobase64(&i);
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        } else {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = encoding_percent_component(&i);
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        }
-                    }
-                    FileContent::Single(chr) => {
-                        if self.flg1 {
-                            Ok((FileContent::Single(encoding_percent_component(&chr)),fileloc))
-                        } else if self.flg2 {
-                            Ok((FileContent::Single(tobase64(&chr)),fileloc))
-                        } else {
-                            Ok((FileContent::Single(encoding_percent_component(&chr)),fileloc))
-                        }
-                    }
-                }
+    //one row per stage of the chain
+    fn linkchain(&self, source: &StringOrPath, fileloc: FileLocate, chain: &CodecChain) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        if self.flg9 {
+            let mut stages = chain.encode_stages(&source.read_bytes()?);
+            let last = stages.pop().unwrap_or_default();
+            let (rows, _) = stagerows(source.label(), stages, Ok(last));
+            let (readinp, processed) = rows.into_iter().unzip();
+            return Ok((FileContent::Multiple(readinp),FileContent::Multiple(processed),fileloc,Vec::new()));
+        }
+        linkstages(source, fileloc, self.output.is_some(), |i| match self.resolved(i) {
+            Ok(inp) => {
+                let mut stages = chain.encode_stages(inp.as_bytes());
+                let last = stages.pop().unwrap_or_default();
+                stagerows(inp, stages, Ok(last))
             }
+            Err(error) => stagerows(i.to_string(), Vec::new(), Err(error)),
+        })
+    }
+
+    //whole URL mode works on the components, everything else goes through the codec
+    fn process(&self, codec: &dyn Codec, inp: &str) -> Result<String, UrlencodeError> {
+        let inp = self.resolved(inp)?;
+        if self.flg5 {
+            Ok(encoding_percent_url(&inp)?)
+        } else {
+            Ok(codec.encode(inp.as_bytes()))
         }
     }
-    pub fn getbefore_process(&self) -> Result<FileContent> {
-        match &self.filetext {
-            StringOrPath::String(inp) => Ok(FileContent::Single(inp.to_string())),
-            StringOrPath::Path(path) => {
-                let filecontent = readfile(&path.clone().into_os_string().into_string().unwrap()).unwrap();
-                match filecontent {
-                    FileContent::Multiple(lines) => Ok(FileContent::Multiple(lines)),
-                    FileContent::Single(chr) => Ok(FileContent::Single(chr)),
-                }
+
+    //apply --base when it is given
+    fn resolved(&self, inp: &str) -> Result<String, UrlencodeError> {
+        match &self.base {
+            Some(base) => Ok(resolve(base, inp)?),
+            None => Ok(inp.to_string()),
+        }
+    }
+
+    //pick the encoder from --codec or the flags, percent component is the default choice
+    pub fn codec(&self, codecs: &CodecRegistry) -> Result<Arc<dyn Codec>, UrlencodeError> {
+        if let Some(name) = &self.codec {
+            codecs.get(name)
+        } else if self.flg6 {
+            codecs.get("form")
+        } else if let Some(alphabet) = self.base32 {
+            Ok(Arc::new(Base32Codec { alphabet, pad: !self.flg8 && alphabet != Base32Alphabet::Crockford }))
+        } else if self.flg13 {
+            Ok(Arc::new(HexCodec { upper: self.flg14, separator: self.separator.unwrap_or(HexSeparator::None) }))
+        } else if let Some(alphabet) = self.base58 {
+            if self.flg15 {
+                Ok(Arc::new(Base58CheckCodec { alphabet, version: self.version_byte }))
+            } else {
+                Ok(Arc::new(Base58Codec { alphabet }))
             }
+        } else if let Some(variant) = self.base85 {
+            Ok(Arc::new(Base85Codec { variant }))
+        } else if let Some(context) = self.entities {
+            Ok(Arc::new(HtmlCodec { context }))
+        } else if self.flg16 {
+            Ok(Arc::new(QuotedPrintableCodec { binary: self.flg9 }))
+        } else if self.flg17 {
+            codecs.get("mime")
+        } else if let Some(name) = &self.header_param {
+            Ok(Arc::new(HeaderParamCodec { name: name.clone(), language: self.lang.clone().unwrap_or_default() }))
+        } else if self.flg2 || self.flg7 || self.flg8 {
+            let alphabet = if self.flg7 {
+                Base64Alphabet::UrlSafe
+            } else {
+                Base64Alphabet::Standard
+            };
+            Ok(Arc::new(Base64Codec { alphabet, pad: !self.flg8 }))
+        } else if self.set.is_some() || self.keep.is_some() {
+            Ok(Arc::new(PercentCodec { set: self.encode_set() }))
+        } else {
+            codecs.get("percent")
+        }
+    }
+
+    pub fn encode_set(&self) -> EncodeSet {
+        let set = self.set.unwrap_or(EncodeSet::LEGACY);
+        match &self.keep {
+            Some(chars) => set.remove_all(chars.as_bytes()),
+            None => set,
         }
     }
+
+    pub fn target(&self) -> OutputTarget {
+        OutputTarget { path: self.output.clone(), clobber: !self.flg12 }
+    }
 }
 
 //ANCHOR - Impl Linking Decode to clap
 impl Form2 {
-    pub fn linking(&self) -> Result<(FileContent,FileLocate), Box<dyn Error>> {
-        let fileloc = if self.flg3 {
-            FileLocate::Html
-        } else if self.flg4 {
-            FileLocate::Terminal
-        } else {
-            FileLocate::Terminal
-        };
-        
-        match &self.filetext {
-            StringOrPath::String(inp) => {
-                if self.flg1 {
-                    Ok((FileContent::Single(decoding_percent(&inp)),fileloc))
-                } else if self.flg2 {
-                    Ok((FileContent::Single(frombase64(&inp).unwrap()),fileloc)) //wiil change when to base64 is finish
-                } else {
-                    Ok((FileContent::Single(decoding_percent(&inp)),fileloc)) //for default choice
-                }
+    //returns the inputs and the results as two columns
+    pub fn linking(&self, codecs: &CodecRegistry) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        let fileloc = filelocate(self.format, self.flg3);
+        let source = self.filetext.forced(self.flg9, self.flg10);
+        if self.flg12 {
+            return linkstages(&source, fileloc, self.output.is_some(), |i| {
+                let mut layers: Vec<String> = decode_recursive(i).into_iter().map(|layer| layer.decoded).collect();
+                let last = layers.pop().unwrap_or_else(|| i.to_string());
+                stagerows(i.to_string(), layers, Ok(last))
+            });
+        }
+        if self.flg16 {
+            return linklines(&source, fileloc, self.output.is_some(), decoding_percent_url);
+        }
+        if let Some(spec) = &self.chain {
+            return self.linkchain(&source, fileloc, &codecs.chain(spec)?);
+        }
+        let codec = self.codec(codecs)?;
+
+        if self.flg8 {
+            let bytes = codec.decode(&self.payload(&source)?)?;
+            let result = self.writebytes(bytes)?;
+            return Ok((FileContent::Single(source.label()),FileContent::Single(result),fileloc,Vec::new()));
+        }
+        if self.flg18 {
+            //a soft line break joins two lines, so the body is not decoded line by line
+            let result = self.render(codec.decode(&self.payload(&source)?)?)?;
+            return Ok((FileContent::Single(source.label()),FileContent::Single(result),fileloc,Vec::new()));
+        }
+        linklines(&source, fileloc, self.output.is_some(), |i| self.render(codec.decode(i)?))
+    }
+
+    //one row per stage of the chain
+    fn linkchain(&self, source: &StringOrPath, fileloc: FileLocate, chain: &CodecChain) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        if self.flg8 {
+            let (stages, last) = chain.decode_stages(&self.payload(source)?);
+            let (rows, error) = stagerows(source.label(), stages, last.and_then(|bytes| self.writebytes(bytes)));
+            if let Some(error) = error {
+                return Err(error);
             }
-            StringOrPath::Path(path) => {
-                let filecontent = readfile(&path.clone().into_os_string().into_string().unwrap())?;
-                match filecontent {
-                    FileContent::Multiple(lines) => {
-                        if self.flg1 {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = decoding_percent(&i);
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        } else if self.flg2 {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = frombase64(&i).unwrap();
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        } else {
-                            let mut processed_lines = Vec::new();
-                            for i in lines {
-                                let a = decoding_percent(&i);
-                                processed_lines.push(a);
-                            }
-                            Ok((FileContent::Multiple(processed_lines),fileloc))
-                        }
-                    }
-                    FileContent::Single(chr) => {
-                        if self.flg1 {
-                            Ok((FileContent::Single(decoding_percent(&chr)),fileloc))
-                        } else if self.flg2 {
-                            Ok((FileContent::Single(frombase64(&chr)?),fileloc))
-                        } else {
-                            Ok((FileContent::Single(decoding_percent(&chr)),fileloc))
-                        }
-                    }
-                }
+            let (readinp, processed) = rows.into_iter().unzip();
+            return Ok((FileContent::Multiple(readinp),FileContent::Multiple(processed),fileloc,Vec::new()));
+        }
+        linkstages(source, fileloc, self.output.is_some(), |i| {
+            let (stages, last) = chain.decode_stages(i);
+            stagerows(i.to_string(), stages, last.and_then(|bytes| self.render(bytes)))
+        })
+    }
+
+    //binary and quoted-printable mode read the whole input as one payload, the alphabet decoders skip line wrapping themselves
+    fn payload(&self, source: &StringOrPath) -> Result<String, UrlencodeError> {
+        Ok(String::from_utf8_lossy(&source.read_bytes()?).into_owned())
+    }
+
+    //binary mode writes the decoded bytes to --output, or shows them when there is none
+    fn writebytes(&self, bytes: Vec<u8>) -> Result<String, UrlencodeError> {
+        match &self.output {
+            Some(path) => {
+                createfile(path, !self.flg11)?.write_all(&bytes)?;
+                Ok(format!("{} bytes written to {}", bytes.len(), path.display()))
             }
+            None if self.flg14 => Ok(hexdump(&bytes)),
+            None => Ok(String::from_utf8_lossy(&bytes).into_owned()),
         }
     }
-    pub fn getbefore_process(&self) -> Result<FileContent> {
-        match &self.filetext {
-            StringOrPath::String(inp) => Ok(FileContent::Single(inp.to_string())),
-            StringOrPath::Path(path) => {
-                let filecontent = readfile(&path.clone().into_os_string().into_string().unwrap()).unwrap();
-                match filecontent {
-                    FileContent::Multiple(lines) => Ok(FileContent::Multiple(lines)),
-                    FileContent::Single(chr) => Ok(FileContent::Single(chr)),
-                }
+
+    //decoded bytes as text, or as a dump with --hexdump
+    fn render(&self, bytes: Vec<u8>) -> Result<String, UrlencodeError> {
+        if self.flg14 {
+            Ok(hexdump(&bytes))
+        } else {
+            bytes_to_string(bytes)
+        }
+    }
+
+    //pick the decoder from --codec or the flags, percent is the default choice
+    pub fn codec(&self, codecs: &CodecRegistry) -> Result<Arc<dyn Codec>, UrlencodeError> {
+        if let Some(name) = &self.codec {
+            codecs.get(name)
+        } else if self.flg5 {
+            codecs.get("form")
+        } else if let Some(alphabet) = self.base32 {
+            Ok(Arc::new(Base32Codec { alphabet, pad: true }))
+        } else if self.flg13 {
+            codecs.get("hex")
+        } else if let Some(alphabet) = self.base58 {
+            if self.flg15 {
+                Ok(Arc::new(Base58CheckCodec { alphabet, version: self.version_byte }))
+            } else {
+                Ok(Arc::new(Base58Codec { alphabet }))
             }
+        } else if let Some(variant) = self.base85 {
+            Ok(Arc::new(Base85Codec { variant }))
+        } else if self.flg17 {
+            codecs.get("html")
+        } else if self.flg18 {
+            Ok(Arc::new(QuotedPrintableCodec { binary: self.flg8 }))
+        } else if self.flg19 {
+            codecs.get("mime")
+        } else if let Some(name) = &self.header_param {
+            Ok(Arc::new(HeaderParamCodec { name: name.clone(), language: String::new() }))
+        } else if self.flg2 || self.flg6 || self.flg7 {
+            codecs.get("base64") //the decoder takes both alphabets, with or without padding
+        } else {
+            codecs.get("percent")
         }
     }
+
+    //in binary mode --output takes the decoded bytes, the report goes to the default place
+    pub fn target(&self) -> OutputTarget {
+        let path = if self.flg8 { None } else { self.output.clone() };
+        OutputTarget { path, clobber: !self.flg11 }
+    }
 }
 
-//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//Impl pathfile
+//ANCHOR - Impl Linking Query to clap
+//value column of a key without '=', "c" and "c=" are different parameters
+const NO_VALUE: &str = "(no value)";
 
-#[derive(Debug, Clone)]
-pub enum StringOrPath {
-    String(String),
-    Path(PathBuf),
+//one row per parameter, the failing line gets a row of its own
+fn queryrows(inp: &str) -> (Vec<(String, String)>, Option<UrlencodeError>) {
+    match parse_query(inp) {
+        Ok((pairs, _)) => (pairs.into_iter().map(|(k, v)| (k, v.unwrap_or_else(|| NO_VALUE.to_string()))).collect(), None),
+        Err(error) => (vec![(inp.to_string(), String::new())], Some(error)),
+    }
 }
 
-impl std::str::FromStr for StringOrPath {
-    type Err = std::io::Error;
+impl Form3 {
+    //returns the keys and values as two columns, one row per parameter,
+    //or the inputs and the rewritten URLs when there is something to edit
+    pub fn linking(&self) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        let fileloc = filelocate(self.format, self.flg3);
+        let source = self.filetext.forced(self.flg6, self.flg7);
+        let edits = self.edits();
+        if !edits.is_empty() {
+            return linklines(&source, fileloc, self.output.is_some(), |i| edit_query(i, &edits));
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match std::fs::canonicalize(s) {
-            Ok(path) => Ok(StringOrPath::Path(path)),
-            Err(_) => Ok(StringOrPath::String(s.to_string())),
+        linkstages(&source, fileloc, self.output.is_some(), queryrows)
+    }
+
+    //removes first, then sets and adds, sorting always comes last
+    pub fn edits(&self) -> Vec<QueryEdit> {
+        let mut edits = Vec::new();
+        for k in &self.remove {
+            edits.push(QueryEdit::Remove(k.clone()));
+        }
+        for (k, v) in &self.set {
+            edits.push(QueryEdit::Set(k.clone(), v.clone()));
+        }
+        for (k, v) in &self.add {
+            edits.push(QueryEdit::Add(k.clone(), v.clone()));
         }
+        if self.flg5 {
+            edits.push(QueryEdit::Sort);
+        }
+        edits
+    }
+
+    pub fn target(&self) -> OutputTarget {
+        OutputTarget { path: self.output.clone(), clobber: !self.flg8 }
     }
 }
 
-//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//For reading from file and saving the result to html table
+//ANCHOR - Impl Linking Normalize to clap
+impl Form4 {
+    //returns the inputs and the results as two columns
+    pub fn linking(&self) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        let fileloc = filelocate(self.format, self.flg3);
+        let options = self.options();
+        let source = self.filetext.forced(self.flg11, self.flg12);
+        linklines(&source, fileloc, self.output.is_some(), |i| Ok(normalize_url(i, &options)?))
+    }
 
-#[derive(Debug)]
-pub enum FileContent {
-    Single(String),
-    Multiple(Vec<String>),
-}
+    pub fn options(&self) -> NormalizeOptions {
+        NormalizeOptions {
+            case: !self.flg5,
+            hex_case: !self.flg6,
+            unreserved: !self.flg7,
+            dot_segments: !self.flg8,
+            default_port: !self.flg9,
+            empty_path: !self.flg10,
+        }
+    }
 
-#[derive(Debug)]
-pub enum FileLocate {
-   Html,
-   Terminal, 
+    pub fn target(&self) -> OutputTarget {
+        OutputTarget { path: self.output.clone(), clobber: !self.flg13 }
+    }
 }
 
-#[derive(Error, Debug)]
-pub struct ErrorToSaveFile {
-    msg:String,
+//ANCHOR - Impl Linking Detect to clap
+impl Form5 {
+    //returns the inputs and the guesses, or the layers with --recursive
+    pub fn linking(&self) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError> {
+        let fileloc = filelocate(self.format, self.flg3);
+        let source = self.filetext.forced(self.flg6, self.flg7);
+        linklines(&source, fileloc, self.output.is_some(), |i| Ok(self.process(i)))
+    }
+
+    //"percent 84, form 79" or "percent > percent", "none" when nothing fits
+    fn process(&self, inp: &str) -> String {
+        let names: Vec<String> = if self.flg5 {
+            decode_recursive(inp).iter().map(|layer| layer.name.to_string()).collect()
+        } else {
+            detect(inp).iter().map(|guess| format!("{} {}", guess.name, guess.score)).collect()
+        };
+        if names.is_empty() {
+            "none".to_string()
+        } else if self.flg5 {
+            names.join(" > ")
+        } else {
+            names.join(", ")
+        }
+    }
+
+    pub fn target(&self) -> OutputTarget {
+        OutputTarget { path: self.output.clone(), clobber: !self.flg8 }
+    }
 }
 
-impl std::fmt::Display for ErrorToSaveFile {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.msg)
+//--format wins over --tohtml, terminal is the default
+fn filelocate(format: Option<FileLocate>, html: bool) -> FileLocate {
+    if let Some(format) = format {
+        format
+    } else if html {
+        FileLocate::Html
+    } else {
+        FileLocate::Terminal
     }
 }
 
-impl ErrorToSaveFile { //create my own error
-    pub fn new(msg:&str) -> Self {
-        ErrorToSaveFile { msg: msg.to_string() }
+//stream stdin line by line when the rows can go straight to stdout, otherwise read everything first
+fn linklines<F>(source: &StringOrPath, fileloc: FileLocate, output: bool, process: F) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError>
+where
+    F: Fn(&str) -> Result<String, UrlencodeError>,
+{
+    if matches!(source, StringOrPath::Stdin) && fileloc != FileLocate::Html && !output {
+        let errors = streamlines(io::stdin().lock(), fileloc, process)?;
+        //rows are already written, an empty terminal result leaves nothing for savefile
+        return Ok((FileContent::Multiple(Vec::new()),FileContent::Multiple(Vec::new()),FileLocate::Terminal,errors));
     }
+
+    let readinp = source.read()?;
+    let (processed, errors) = process_content(&readinp, process);
+    Ok((readinp,processed,fileloc,errors))
 }
 
-//ANCHOR - Readfile
-pub fn readfile(filename: &str) -> Result<FileContent, Box<dyn Error>> {
-    let content = fs::read_to_string(filename)?;
-    let lines: Vec<String> = content.lines().map(|f| f.to_string()).collect();
+//same as linklines, but one line can give several rows
+fn linkstages<F>(source: &StringOrPath, fileloc: FileLocate, output: bool, process: F) -> Result<(FileContent,FileContent,FileLocate,Vec<LineError>), UrlencodeError>
+where
+    F: Fn(&str) -> (Vec<(String, String)>, Option<UrlencodeError>),
+{
+    if matches!(source, StringOrPath::Stdin) && fileloc != FileLocate::Html && !output {
+        let errors = streamstages(io::stdin().lock(), fileloc, process)?;
+        return Ok((FileContent::Multiple(Vec::new()),FileContent::Multiple(Vec::new()),FileLocate::Terminal,errors));
+    }
 
-    match lines.len() {
-        0 => Err("Empty".into()),
-        1 => Ok(FileContent::Single(lines[0].clone())),
-        _ => Ok(FileContent::Multiple(lines)),
+    let mut readinp = Vec::new();
+    let mut processed = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, i) in source.read()?.into_lines().iter().enumerate() {
+        let (rows, error) = process(i);
+        for (input, result) in rows {
+            readinp.push(input);
+            processed.push(result);
+        }
+        if let Some(error) = error {
+            errors.push(LineError { line: idx + 1, row: processed.len() - 1, error });
+        }
     }
+    Ok((FileContent::Multiple(readinp),FileContent::Multiple(processed),fileloc,errors))
 }
 
-//ANCHOR - Savefile
-pub fn savefile(oinp:FileContent, wtop:FileContent, destinate: FileLocate) -> Result<(),ErrorToSaveFile> {
-    match wtop {
-        FileContent::Single(n) => {
-            match destinate {
-                FileLocate::Html => {
-                    let mut outfile = File::create("output.html").unwrap();
-                    outfile.write_all(b"<style>\ntable, th, td {\n    border:1px solid black;\n}\n").unwrap();
-                    outfile.write_all(b"</style>\n\n<table>\n    <tr>\n").unwrap();
-                    outfile.write_all(b"        <th>Input</th>\n        <th>Result</th>\n").unwrap();
-                    outfile.write_all(b"    </tr>").unwrap();
-                    outfile.write_all(b"    <tr>\n").unwrap();
-                    match oinp {
-                        FileContent::Single(x) => {
-                            outfile.write_all(format!("        <td>{}</td>\n", x).as_bytes()).unwrap();
-                        }
-                        // FileContent::Multiple(y) => {
-                        //     for item in y {
-                        //         outfile.write_all(format!("        <td>{}</td>\n", item).as_bytes()).unwrap();
-                        //     }
-                        // }
-                        _ => println!("Err")
-                    }
+//turn the stage results into (input, result) rows, the input of a stage is the result of the one before,
+//a failing last stage keeps an empty result and hands its error back
+pub fn stagerows(inp: String, stages: Vec<String>, last: Result<String, UrlencodeError>) -> (Vec<(String, String)>, Option<UrlencodeError>) {
+    let mut rows = Vec::new();
+    let mut input = inp;
+    for stage in stages {
+        rows.push((input, stage.clone()));
+        input = stage;
+    }
+    match last {
+        Ok(result) => {
+            rows.push((input, result));
+            (rows, None)
+        }
+        Err(error) => {
+            rows.push((input, String::new()));
+            (rows, Some(error))
+        }
+    }
+}
 
-                    outfile.write_all(format!("        <td>{}</td>\n",n).as_bytes()).unwrap();
-                    outfile.write_all(b"    </tr>\n").unwrap();
-                    outfile.write_all(b"</table>\n").unwrap();
-                }
-                FileLocate::Terminal => {
-                    match oinp {
-                        FileContent::Single(x) => {println!("\nInput: {}\nResult: {}\n",x,n); return Ok(())}
-                        _ => {println!("Er"); return Ok(())}
-                    }
-                }
-            } 
+//run `process` on every line, a failing line keeps an empty result and its error is collected
+fn process_content<F>(content: &FileContent, process: F) -> (FileContent, Vec<LineError>)
+where
+    F: Fn(&str) -> Result<String, UrlencodeError>,
+{
+    match content {
+        FileContent::Single(chr) => match process(chr) {
+            Ok(a) => (FileContent::Single(a), Vec::new()),
+            Err(error) => (FileContent::Single(String::new()), vec![LineError { line: 1, row: 0, error }]),
         },
-        FileContent::Multiple(st) => {
-            match destinate {
-                FileLocate::Html => {
-                    let mut outfile = File::create("output.html").unwrap();
-                    outfile.write_all(b"<style>\ntable, th, td {\n    border:1px solid black;\n}\n").unwrap();
-                    outfile.write_all(b"</style>\n\n<table>\n    <tr>\n").unwrap();
-                    outfile.write_all(b"        <th>Input</th>\n        <th>Result</th>\n").unwrap();
-                    outfile.write_all(b"    </tr>").unwrap();
-                    match oinp {
-                        FileContent::Single(x) => {
-                            outfile.write_all(format!("        <td>{}</td>\n", x).as_bytes()).unwrap();
-                        }
-                        FileContent::Multiple(y) => {
-                            for item in 0..y.len() {
-                                outfile.write_all(b"    <tr>\n").unwrap();
-                                outfile.write_all(format!("        <td>{}</td>\n", y[item]).as_bytes()).unwrap();
-                                outfile.write_all(format!("        <td>{}</td>\n", st[item]).as_bytes()).unwrap();
-                                outfile.write_all(b"    </tr>\n").unwrap();
-                            }
-                            
-                        }
-                    }
-                    outfile.write_all(b"</table>\n").unwrap();
-                }
-                FileLocate::Terminal => {
-                    match oinp {
-                        FileContent::Multiple(x) => {
-                            for i in 0..x.len() {
-                                println!("\nInput: {}\nResult: {}\n",x[i],st[i]);
-                            }
-                            return Ok(())
-                        }
-                        _ => {println!("Er"); return Ok(())}
+        FileContent::Multiple(lines) => {
+            let mut processed_lines = Vec::new();
+            let mut errors = Vec::new();
+            for (idx, i) in lines.iter().enumerate() {
+                match process(i) {
+                    Ok(a) => processed_lines.push(a),
+                    Err(error) => {
+                        processed_lines.push(String::new());
+                        errors.push(LineError { line: idx + 1, row: idx, error });
                     }
                 }
             }
+            (FileContent::Multiple(processed_lines), errors)
         }
     }
-    
-    Err(ErrorToSaveFile::new("Error to return"))
 }
 
-
 //------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//Percent Encoding and Decoding
-
-//ANCHOR - Encode Percent
-pub fn encoding_percent_component(inp: &str) -> String {
-    let mut buffer = Vec::new();
-    for &i in inp.as_bytes() {
-        match i {
-            b'A'..=b'Z'
-            | b'a'..=b'z'
-            | b'0'..=b'9'
-            | b'$'
-            | b'-'
-            | b'_'
-            | b'.'
-            | b'+'
-            | b'!'
-            | b'*'
-            | b'('
-            | b')' => buffer.push(i),
-            _ => {
-                buffer.push(b'%');
-                buffer.push(binoperate1(i >> 4)); //for getting 4bits high| example pass i>>4 = 3
-                buffer.push(binoperate1(i & 0xF)); //for getting 4bits low| example pass i & 0xF = 2
-            }
-        }
-    }
-    unsafe { String::from_utf8_unchecked(buffer) }
-}
+//Impl pathfile
 
-pub fn binoperate1(digit: u8) -> u8 {
-    match digit {
-        0..=9 => b'0' + digit,        //it start from 48 + ... it return a number
-        10..=15 => b'A' + digit - 10, //start from 65 + ... - 10 it will return a char
-        _ => panic!("Invalid input: {}", digit),
+#[derive(Debug, Clone)]
+pub enum StringOrPath {
+    String(String),
+    Path(PathBuf),
+    Stdin,
+}
+
+impl std::str::FromStr for StringOrPath {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            Ok(StringOrPath::Stdin)
+        } else if std::path::Path::new(s).is_file() {
+            Ok(StringOrPath::Path(PathBuf::from(s)))
+        } else {
+            Ok(StringOrPath::String(s.to_string()))
+        }
     }
 }
 
-//ANCHOR - Decode Percent
-pub fn decoding_percent(inp: &str) -> String {
-    let mut buffer: Vec<u8> = Vec::new();
-    let mut bytes = inp.as_bytes().iter();
-    while let Some(&b) = bytes.next() {
-        match b {
-            b'%' => {
-                if let (Some(&first), Some(&second)) = (bytes.next(), bytes.next()) {
-                    if let (Some(v1), Some(v2)) = (binoperate2(first), binoperate2(second)) {
-                        buffer.push((v1 << 4) | v2);
-                    } else {
-                        buffer.push(b'%');
-                        buffer.push(first);
-                        buffer.push(second);
-                    }
-                } else {
-                    buffer.push(b'%');
-                }
+impl StringOrPath {
+    //apply --text or --file over the guess made by from_str
+    pub fn forced(&self, text: bool, file: bool) -> StringOrPath {
+        match self {
+            StringOrPath::Path(path) if text => StringOrPath::String(path.to_string_lossy().into_owned()),
+            StringOrPath::Stdin if text => StringOrPath::String("-".to_string()),
+            StringOrPath::String(inp) if file => StringOrPath::Path(PathBuf::from(inp)),
+            StringOrPath::Stdin if file => StringOrPath::Path(PathBuf::from("-")),
+            other => other.clone(),
+        }
+    }
+
+    pub fn read(&self) -> Result<FileContent, UrlencodeError> {
+        match self {
+            StringOrPath::String(inp) => Ok(FileContent::Single(inp.to_string())),
+            StringOrPath::Path(path) => readfile(&path.to_string_lossy()),
+            StringOrPath::Stdin => {
+                let mut content = String::new();
+                io::stdin().read_to_string(&mut content)?;
+                splitlines(&content)
             }
-            _ => buffer.push(b),
         }
     }
-    String::from_utf8(buffer).expect("Invalid UTF-8 sequence")
-}
 
-pub fn binoperate2(inp: u8) -> Option<u8> {
-    match inp {
-        b'0'..=b'9' => Some(inp - b'0'),
-        b'A'..=b'F' => Some(inp - b'A' + 10),
-        b'a'..=b'f' => Some(inp - b'a' + 10),
-        _ => None,
+    //what the input column shows when the whole input is one payload
+    pub fn label(&self) -> String {
+        match self {
+            StringOrPath::String(inp) => inp.clone(),
+            StringOrPath::Path(path) => path.display().to_string(),
+            StringOrPath::Stdin => "<stdin>".to_string(),
+        }
     }
-} //found % it will remove % and next 2 digit
+
+    pub fn read_bytes(&self) -> Result<Vec<u8>, UrlencodeError> {
+        match self {
+            StringOrPath::String(inp) => Ok(inp.as_bytes().to_vec()),
+            StringOrPath::Path(path) => readfile_bytes(&path.to_string_lossy()),
+            StringOrPath::Stdin => {
+                let mut content = Vec::new();
+                io::stdin().read_to_end(&mut content)?;
+                Ok(content)
+            }
+        }
+    }
+}
 
 //------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//Base64 Misc: Matching, trait, etc.
+//For reading from file and saving the result to html table
 
-fn matchingb64(inp: u8) -> u8 {
-    match inp {
-        0..=25 => b'A' + inp,
-        26..=51 => b'a' + (inp - 26),
-        52..=61 => b'0' + (inp - 52),
-        62 => b'+',
-        63 => b'/',
-        _ => panic!("Invalid"),
+#[derive(Debug)]
+pub enum FileContent {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl FileContent {
+    pub fn into_lines(self) -> Vec<String> {
+        match self {
+            FileContent::Single(chr) => vec![chr],
+            FileContent::Multiple(lines) => lines,
+        }
     }
 }
 
-fn matchingb642(inp: u8) -> Option<u8> {
-    match inp {
-        b'A'..=b'Z' => Some(inp - b'A'),
-        b'a'..=b'z' => Some(inp - b'a' + 26),
-        b'0'..=b'9' => Some(inp - b'0' + 52),
-        b'+' => Some(62),
-        b'/' => Some(63),
-        _ => None,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLocate {
+   Html,
+   Terminal, 
+   Plain,
+   Json,
+   Ndjson,
+   Csv,
+   Tsv,
+   Markdown,
+}
+
+impl FileLocate {
+    pub fn writer(&self) -> Box<dyn RowWriter> {
+        match self {
+            FileLocate::Html => Box::<HtmlWriter>::default(),
+            FileLocate::Terminal => Box::new(TerminalWriter),
+            FileLocate::Plain => Box::new(PlainWriter),
+            FileLocate::Json => Box::<JsonWriter>::default(),
+            FileLocate::Ndjson => Box::new(NdjsonWriter),
+            FileLocate::Csv => Box::new(CsvWriter),
+            FileLocate::Tsv => Box::new(TsvWriter),
+            FileLocate::Markdown => Box::new(MarkdownWriter),
+        }
+    }
+}
+
+///Where savefile writes, stdout when there is no path and the format is not html
+#[derive(Debug, Clone, Default)]
+pub struct OutputTarget {
+    pub path: Option<PathBuf>,
+    pub clobber: bool, //overwrite an existing file
+}
+
+impl std::str::FromStr for FileLocate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "html" => Ok(FileLocate::Html),
+            "terminal" => Ok(FileLocate::Terminal),
+            "plain" => Ok(FileLocate::Plain),
+            "json" => Ok(FileLocate::Json),
+            "ndjson" | "jsonl" => Ok(FileLocate::Ndjson),
+            "csv" => Ok(FileLocate::Csv),
+            "tsv" => Ok(FileLocate::Tsv),
+            "markdown" | "md" => Ok(FileLocate::Markdown),
+            _ => Err(format!(
+                "unknown format '{}' (terminal, html, plain, json, ndjson, csv, tsv, markdown)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum UrlencodeError {
+    #[error("invalid character {byte:#04x} at byte {offset}")]
+    InvalidCharacter { offset: usize, byte: u8 },
+    #[error("bad padding at byte {offset}")]
+    BadPadding { offset: usize },
+    #[error("truncated escape at byte {offset}")]
+    TruncatedEscape { offset: usize },
+    #[error("invalid UTF-8 sequence at decoded byte {offset}")]
+    InvalidUtf8 { offset: usize }, //counted in the decoded bytes, not in the input
+    #[error("expected 1 to 3 bytes, got {0}")]
+    InvalidLength(usize),
+    #[error("input is empty")]
+    Empty,
+    #[error("expected a version byte and a 4-byte checksum, got {0} bytes")]
+    MissingChecksum(usize),
+    #[error("checksum mismatch: expected {expected:08x}, found {found:08x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    #[error("expected version byte {expected:#04x}, found {found:#04x}")]
+    VersionMismatch { expected: u8, found: u8 },
+    #[error("invalid punycode at byte {offset}")]
+    InvalidPunycode { offset: usize },
+    #[error("unsupported charset '{0}'")]
+    UnsupportedCharset(String),
+    #[error("expected charset'language'value, got '{0}'")]
+    InvalidExtValue(String),
+    #[error("no '{0}' parameter")]
+    MissingParameter(String),
+    #[error("unknown codec '{name}' (known: {known})")]
+    UnknownCodec { name: String, known: String },
+    ///Failure of a codec that is not part of this crate, `"message".into()` or any error type works
+    #[error(transparent)]
+    Codec(Box<dyn std::error::Error + Send + Sync>),
+    #[error(transparent)]
+    Url(#[from] UrlError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl UrlencodeError {
+    //exit status for the CLI, following sysexits.h
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            UrlencodeError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => 66, //EX_NOINPUT
+            UrlencodeError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists => 73, //EX_CANTCREAT
+            UrlencodeError::Io(_) => 74, //EX_IOERR
+            UrlencodeError::Empty => 66,
+            UrlencodeError::UnknownCodec { .. } => 64, //EX_USAGE
+            _ => 65, //EX_DATAERR
+        }
+    }
+
+    //move the offset when the failing input was a slice of a longer line
+    pub fn offset_by(self, n: usize) -> Self {
+        match self {
+            UrlencodeError::InvalidCharacter { offset, byte } => UrlencodeError::InvalidCharacter { offset: offset + n, byte },
+            UrlencodeError::BadPadding { offset } => UrlencodeError::BadPadding { offset: offset + n },
+            UrlencodeError::TruncatedEscape { offset } => UrlencodeError::TruncatedEscape { offset: offset + n },
+            UrlencodeError::InvalidPunycode { offset } => UrlencodeError::InvalidPunycode { offset: offset + n },
+            other => other,
+        }
+    }
+}
+
+///Error of one input line, the other lines are still processed
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize, //1-based line of the input
+    pub row: usize,  //0-based row of the output the error belongs to
+    pub error: UrlencodeError,
+}
+
+pub fn bytes_to_string(bytes: Vec<u8>) -> Result<String, UrlencodeError> {
+    String::from_utf8(bytes).map_err(|e| UrlencodeError::InvalidUtf8 { offset: e.utf8_error().valid_up_to() })
+}
+
+//ANCHOR - Readfile
+pub fn readfile(filename: &str) -> Result<FileContent, UrlencodeError> {
+    let content = fs::read_to_string(filename)?;
+    splitlines(&content)
+}
+
+fn splitlines(content: &str) -> Result<FileContent, UrlencodeError> {
+    let lines: Vec<String> = content.lines().map(|f| f.to_string()).collect();
+
+    match lines.len() {
+        0 => Err(UrlencodeError::Empty),
+        1 => Ok(FileContent::Single(lines[0].clone())),
+        _ => Ok(FileContent::Multiple(lines)),
+    }
+}
+
+//ANCHOR - Readfile as bytes, for binary payloads
+pub fn readfile_bytes(filename: &str) -> Result<Vec<u8>, UrlencodeError> {
+    Ok(fs::read(filename)?)
+}
+
+//ANCHOR - Stream lines
+//process and print one line at a time, so the CLI can sit in the middle of a shell pipeline
+pub fn streamlines<R, F>(reader: R, destinate: FileLocate, process: F) -> Result<Vec<LineError>, UrlencodeError>
+where
+    R: BufRead,
+    F: Fn(&str) -> Result<String, UrlencodeError>,
+{
+    streamstages(reader, destinate, |line| stagerows(line.to_string(), Vec::new(), process(line)))
+}
+
+//same as streamlines, but one line can give several rows
+pub fn streamstages<R, F>(reader: R, destinate: FileLocate, process: F) -> Result<Vec<LineError>, UrlencodeError>
+where
+    R: BufRead,
+    F: Fn(&str) -> (Vec<(String, String)>, Option<UrlencodeError>),
+{
+    let mut writer = destinate.writer();
+    let mut out = io::stdout().lock();
+    let mut errors = Vec::new();
+    let mut count = 0;
+    writer.begin(&mut out)?;
+    for (idx, line) in reader.lines().enumerate() {
+        let (rows, error) = process(&line?);
+        let count_rows = rows.len();
+        let last = count_rows.saturating_sub(1); //a URL without a query has no rows
+        let error = error.map(|error| {
+            let message = error.to_string();
+            errors.push(LineError { line: idx + 1, row: count + last, error });
+            message
+        });
+        for (n, (input, result)) in rows.into_iter().enumerate() {
+            let error = if n == last { error.clone() } else { None };
+            writer.row(&mut out, &Row { input, result, error })?;
+        }
+        count += count_rows;
+        out.flush()?;
+    }
+    writer.end(&mut out)?;
+    Ok(errors)
+}
+
+//ANCHOR - Savefile
+pub fn savefile(oinp:FileContent, wtop:FileContent, destinate: FileLocate, errors: &[LineError], target: &OutputTarget) -> Result<(),UrlencodeError> {
+    let rows = torows(oinp, wtop, errors);
+    let mut writer = destinate.writer();
+
+    let path = match (&target.path, destinate) {
+        (Some(path), _) => Some(path.clone()),
+        (None, FileLocate::Html) => Some(PathBuf::from("output.html")),
+        (None, _) => None,
+    };
+    match path {
+        Some(path) => {
+            let mut outfile = BufWriter::new(createfile(&path, target.clobber)?);
+            writerows(writer.as_mut(), &rows, &mut outfile)?;
+            outfile.flush()?;
+        }
+        None => writerows(writer.as_mut(), &rows, &mut io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+//with clobber off an existing file is an AlreadyExists error instead of being overwritten
+pub fn createfile(path: &std::path::Path, clobber: bool) -> Result<File, UrlencodeError> {
+    if clobber {
+        Ok(File::create(path)?)
+    } else {
+        Ok(fs::OpenOptions::new().write(true).create_new(true).open(path)?)
     }
 }
 
+
 //------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//Base64 Encoding and Decoding
+//Output formats
 
-//ANCHOR - Encode Base64
-pub fn tobase64(inp: &str) -> String {
-    let iter: Vec<_> = inp.as_bytes().chunks(3).collect(); //type: [[u8,u8,u8]] (3 bytes)
-    let mut result = Vec::new();
-    let mut checking: u8 = 0;
-    for i in iter {
-        if i.len() == 2 {
-            checking = 2;
-        } else if i.len() == 1 {
-            checking = 1;
+///One output row, every format writes these three fields
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Row {
+    pub input: String,
+    pub result: String,
+    pub error: Option<String>,
+}
+
+///Writes rows in one output format, implement it to plug in a new format
+pub trait RowWriter {
+    fn begin(&mut self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()>;
+    fn end(&mut self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn writerows(writer: &mut dyn RowWriter, rows: &[Row], out: &mut dyn Write) -> io::Result<()> {
+    writer.begin(out)?;
+    for row in rows {
+        writer.row(out, row)?;
+    }
+    writer.end(out)
+}
+
+//pair up inputs and results, errors are matched by their row
+pub fn torows(oinp: FileContent, wtop: FileContent, errors: &[LineError]) -> Vec<Row> {
+    oinp.into_lines()
+        .into_iter()
+        .zip(wtop.into_lines())
+        .enumerate()
+        .map(|(idx, (input, result))| Row {
+            input,
+            result,
+            error: errors.iter().find(|e| e.row == idx).map(|e| e.error.to_string()),
+        })
+        .collect()
+}
+
+//ANCHOR - HTML writer
+//self-contained report: inline css and js, no external assets
+#[derive(Default)]
+pub struct HtmlWriter {
+    rows: usize,
+}
+
+const HTML_STYLE: &str = r##"<style>
+body { font-family: sans-serif; margin: 1em; }
+table, th, td {
+    border:1px solid black;
+}
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: .3em .5em; vertical-align: top; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th.asc::after { content: " \25B2"; }
+th.desc::after { content: " \25BC"; }
+.text { font-family: monospace; white-space: pre-wrap; word-break: break-all; }
+td.input mark { background: #ffc9c9; }
+td.result mark { background: #c4f0c4; }
+tr.error td { background: #fff0f0; }
+button.copy { float: right; margin-left: .5em; font-size: .75em; }
+#filter { width: 100%; max-width: 40em; padding: .4em; margin-bottom: .3em; }
+#count { color: #555; margin-bottom: .8em; }
+</style>
+"##;
+
+const HTML_SCRIPT: &str = r##"<script>
+(function () {
+  var filter = document.getElementById("filter");
+  var count = document.getElementById("count");
+  var body = document.querySelector("#report tbody");
+  var heads = document.querySelectorAll("#report th");
+  function text(row) {
+    return Array.prototype.map.call(row.querySelectorAll(".text"), function (span) {
+      return span.textContent;
+    }).join("\n").toLowerCase();
+  }
+  function update() {
+    var q = filter.value.toLowerCase();
+    var shown = 0;
+    Array.prototype.forEach.call(body.rows, function (row) {
+      var hit = text(row).indexOf(q) !== -1;
+      row.style.display = hit ? "" : "none";
+      if (hit) shown++;
+    });
+    var errors = body.querySelectorAll("tr.error").length;
+    count.textContent = shown + " of " + body.rows.length + " rows shown, " + errors + " with errors";
+  }
+  filter.addEventListener("input", update);
+  Array.prototype.forEach.call(heads, function (th, col) {
+    th.addEventListener("click", function () {
+      var asc = !th.classList.contains("asc");
+      Array.prototype.forEach.call(heads, function (h) { h.classList.remove("asc", "desc"); });
+      th.classList.add(asc ? "asc" : "desc");
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[col].querySelector(".text").textContent;
+        var y = b.cells[col].querySelector(".text").textContent;
+        var c = x.localeCompare(y, undefined, { numeric: true });
+        return asc ? c : -c;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+  body.addEventListener("click", function (e) {
+    var button = e.target.closest("button.copy");
+    if (!button) return;
+    var value = button.parentNode.querySelector(".text").textContent;
+    function done() {
+      button.textContent = "Copied";
+      setTimeout(function () { button.textContent = "Copy"; }, 1000);
+    }
+    if (navigator.clipboard && window.isSecureContext) {
+      navigator.clipboard.writeText(value).then(done);
+    } else {
+      var area = document.createElement("textarea");
+      area.value = value;
+      document.body.appendChild(area);
+      area.select();
+      document.execCommand("copy");
+      document.body.removeChild(area);
+      done();
+    }
+  });
+  update();
+})();
+</script>
+"##;
+
+impl RowWriter for HtmlWriter {
+    fn begin(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(b"<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>URLencode result</title>\n")?;
+        out.write_all(HTML_STYLE.as_bytes())?;
+        out.write_all(b"</head>\n<body>\n")?;
+        out.write_all(b"<input id=\"filter\" type=\"search\" placeholder=\"Filter rows\" autofocus>\n<div id=\"count\"></div>\n")?;
+        out.write_all(b"<table id=\"report\">\n<thead>\n    <tr>\n")?;
+        out.write_all(b"        <th>#</th>\n        <th>Input</th>\n        <th>Result</th>\n        <th>Error</th>\n")?;
+        out.write_all(b"    </tr>\n</thead>\n<tbody>\n")
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        self.rows += 1;
+        let error = row.error.as_deref().unwrap_or_default();
+        let (input, result) = if row.error.is_some() {
+            (encoding_html(&row.input, HtmlContext::Text), encoding_html(&row.result, HtmlContext::Text))
+        } else {
+            let (removed, added) = diffmarks(&row.input, &row.result);
+            (htmlmarked(&row.input, &removed), htmlmarked(&row.result, &added))
+        };
+        let copy = "<button class=\"copy\" type=\"button\">Copy</button>";
+
+        out.write_all(if row.error.is_some() { b"    <tr class=\"error\">\n" } else { b"    <tr>\n" })?;
+        out.write_all(format!("        <td><span class=\"text\">{}</span></td>\n", self.rows).as_bytes())?;
+        out.write_all(format!("        <td class=\"input\">{}<span class=\"text\">{}</span></td>\n", copy, input).as_bytes())?;
+        out.write_all(format!("        <td class=\"result\">{}<span class=\"text\">{}</span></td>\n", copy, result).as_bytes())?;
+        out.write_all(format!("        <td><span class=\"text\">{}</span></td>\n", encoding_html(error, HtmlContext::Text)).as_bytes())?;
+        out.write_all(b"    </tr>\n")
+    }
+    fn end(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(b"</tbody>\n</table>\n")?;
+        out.write_all(HTML_SCRIPT.as_bytes())?;
+        out.write_all(b"</body>\n</html>\n")
+    }
+}
+
+//flags the chars of `a` and `b` that are not in their longest common subsequence,
+//so "a b" -> "a%20b" marks the space in the input and "%20" in the result
+fn diffmarks(a: &str, b: &str) -> (Vec<bool>, Vec<bool>) {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut removed = vec![false; a.len()];
+    let mut added = vec![false; b.len()];
+
+    //the common prefix and suffix never change, only the middle needs the table
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    if ma.len() * mb.len() > 4_000_000 {
+        return (removed, added); //too long to diff, leave it unmarked
+    }
+
+    let width = mb.len() + 1;
+    let mut table = vec![0u32; (ma.len() + 1) * width];
+    for i in (0..ma.len()).rev() {
+        for j in (0..mb.len()).rev() {
+            table[i * width + j] = if ma[i] == mb[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
         }
+    }
 
-        let a = binoperate3(i).unwrap();
-        for j in a {
-            result.push(matchingb64(j));
+    let (mut i, mut j) = (0, 0);
+    while i < ma.len() && j < mb.len() {
+        if ma[i] == mb[j] {
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            removed[prefix + i] = true;
+            i += 1;
+        } else {
+            added[prefix + j] = true;
+            j += 1;
         }
     }
+    for flag in &mut removed[prefix + i..a.len() - suffix] {
+        *flag = true;
+    }
+    for flag in &mut added[prefix + j..b.len() - suffix] {
+        *flag = true;
+    }
+    (removed, added)
+}
 
-    let returnstring = String::from_utf8(result).expect("Invalid");
-    match checking {
-        1 => format!("{}==", returnstring),
-        2 => format!("{}=", returnstring),
-        _ => returnstring,
+//escape `inp` and wrap every run of flagged chars in <mark>
+fn htmlmarked(inp: &str, flags: &[bool]) -> String {
+    let mut buffer = String::new();
+    let mut marking = false;
+    for (c, &flag) in inp.chars().zip(flags) {
+        if flag != marking {
+            buffer.push_str(if flag { "<mark>" } else { "</mark>" });
+            marking = flag;
+        }
+        buffer.push_str(&encoding_html(c.encode_utf8(&mut [0; 4]), HtmlContext::Text));
+    }
+    if marking {
+        buffer.push_str("</mark>");
     }
+    buffer
 }
 
-pub fn binoperate3(inp: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
-    //make it to binary format
-    match inp.len() {
-        3 => {
-            let res = format!("{:08b}{:08b}{:08b}", inp[0], inp[1], inp[2]);
-            let newbase64 = res
-                .as_bytes()
-                .chunks(6)
-                .map(str::from_utf8)
-                .collect::<Result<Vec<&str>, _>>()
-                .unwrap();
-            let newbase64s: Vec<u8> = newbase64
-                .iter()
-                .map(|&b| u8::from_str_radix(b, 2).unwrap())
-                .collect();
-            Ok(newbase64s)
+//ANCHOR - Terminal writer
+pub struct TerminalWriter;
+
+impl RowWriter for TerminalWriter {
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        if let Some(error) = &row.error {
+            writeln!(out, "\nInput: {}\nError: {}\n", row.input, error)
+        } else if row.result.contains('\n') {
+            writeln!(out, "\nInput: {}\nResult:\n{}\n", row.input, row.result) //a dump starts on its own line
+        } else {
+            writeln!(out, "\nInput: {}\nResult: {}\n", row.input, row.result)
         }
-        2 => {
-            let res = format!("{:08b}{:08b}", inp[0], inp[1]);
-            let mut newbase64 = res
-                .as_bytes()
-                .chunks(6)
-                .map(str::from_utf8)
-                .collect::<Result<Vec<&str>, _>>()
-                .unwrap();
-            let mut adjust = newbase64[newbase64.len() - 1].to_string();
+    }
+}
 
-            if let Some(last_element) = newbase64.last_mut() {
-                while adjust.len() < 6 {
-                    adjust.push('0');
-                }
-                *last_element = &adjust;
-            }
+//ANCHOR - Plain writer
+pub struct PlainWriter;
 
-            let newbase64s: Vec<u8> = newbase64
-                .iter()
-                .map(|&b| u8::from_str_radix(b, 2).unwrap())
-                .collect();
-            Ok(newbase64s)
+impl RowWriter for PlainWriter {
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        match &row.error {
+            Some(error) => writeln!(out, "{} -> error: {}", row.input, error),
+            None => writeln!(out, "{} -> {}", row.input, row.result),
         }
-        1 => {
-            let res = format!("{:08b}", inp[0]);
-            let mut newbase64 = res
-                .as_bytes()
-                .chunks(6)
-                .map(str::from_utf8)
-                .collect::<Result<Vec<&str>, _>>()
-                .unwrap();
-            let mut adjust = newbase64[newbase64.len() - 1].to_string();
+    }
+}
 
-            if let Some(last_element) = newbase64.last_mut() {
-                while adjust.len() < 6 {
-                    adjust.push('0');
-                }
-                *last_element = &adjust;
-            }
+//ANCHOR - JSON writers
+#[derive(Default)]
+pub struct JsonWriter {
+    rows: usize,
+}
 
-            let newbase64s: Vec<u8> = newbase64
-                .iter()
-                .map(|&b| u8::from_str_radix(b, 2).unwrap())
-                .collect();
-            Ok(newbase64s)
+impl RowWriter for JsonWriter {
+    fn begin(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "[")
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        let sep = if self.rows == 0 { "\n" } else { ",\n" };
+        self.rows += 1;
+        write!(out, "{}  {}", sep, jsonobject(row))
+    }
+    fn end(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if self.rows > 0 {
+            writeln!(out)?;
         }
-        _ => Err("Invalid".into()),
+        writeln!(out, "]")
     }
 }
 
-//ANCHOR - Decode Base64
-fn frombase64(inp: &str) -> Result<String, Box<dyn Error>> {
-    let rmpad = inp.trim_end_matches('='); //remove = at the end
-    let mut buffer = Vec::new();
+pub struct NdjsonWriter;
 
-    for i in rmpad.as_bytes() {
-        let rev1 = matchingb642(*i).unwrap();
-        buffer.push(rev1);
+impl RowWriter for NdjsonWriter {
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        writeln!(out, "{}", jsonobject(row))
     }
+}
 
-    let mut decoded_bytes = Vec::new();
-    for i in 0..(buffer.len() - 1){
-        match i % 4 {
-            0 => decoded_bytes.push((buffer[i] << 2) | (buffer[i + 1] >> 4)),
-            1 => decoded_bytes.push(((buffer[i] & 0b1111) << 4) | (buffer[i + 1] >> 2)),
-            2 => decoded_bytes.push(((buffer[i] & 0b11) << 6) | buffer[i + 1]),
-            _ => (),
+fn jsonobject(row: &Row) -> String {
+    let error = match &row.error {
+        Some(error) => format!("\"{}\"", jsonescape(error)),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"input\":\"{}\",\"result\":\"{}\",\"error\":{}}}",
+        jsonescape(&row.input),
+        jsonescape(&row.result),
+        error
+    )
+}
+
+fn jsonescape(inp: &str) -> String {
+    let mut buffer = String::new();
+    for c in inp.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c if (c as u32) < 0x20 => buffer.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buffer.push(c),
         }
     }
-    String::from_utf8(decoded_bytes).map_err(|e| e.into())
+    buffer
 }
 
-//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
-//Unit test
+//ANCHOR - CSV and TSV writers
+pub struct CsvWriter;
+
+impl RowWriter for CsvWriter {
+    fn begin(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "input,result,error")
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        let error = row.error.as_deref().unwrap_or_default();
+        writeln!(out, "{},{},{}", csvfield(&row.input), csvfield(&row.result), csvfield(error))
+    }
+}
+
+//RFC 4180: quote fields holding a separator, quote or line break, double the quotes inside
+fn csvfield(inp: &str) -> String {
+    if inp.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", inp.replace('"', "\"\""))
+    } else {
+        inp.to_string()
+    }
+}
+
+pub struct TsvWriter;
+
+impl RowWriter for TsvWriter {
+    fn begin(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "input\tresult\terror")
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        let error = row.error.as_deref().unwrap_or_default();
+        writeln!(out, "{}\t{}\t{}", tsvfield(&row.input), tsvfield(&row.result), tsvfield(error))
+    }
+}
+
+//tabs and line breaks can not appear in a TSV field, write them as escapes
+fn tsvfield(inp: &str) -> String {
+    inp.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+//ANCHOR - Markdown writer
+pub struct MarkdownWriter;
+
+impl RowWriter for MarkdownWriter {
+    fn begin(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "| Input | Result | Error |\n| --- | --- | --- |")
+    }
+    fn row(&mut self, out: &mut dyn Write, row: &Row) -> io::Result<()> {
+        let error = row.error.as_deref().unwrap_or_default();
+        writeln!(out, "| {} | {} | {} |", markdownfield(&row.input), markdownfield(&row.result), markdownfield(error))
+    }
+}
+
+fn markdownfield(inp: &str) -> String {
+    inp.replace('\\', "\\\\").replace('|', "\\|").replace('\n', "<br>")
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Percent-encode sets
+
+///Set of bytes that percent-encoding turns into %XX, non-ASCII bytes are always in the set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeSet {
+    ascii: u128, //bit n set means byte n is encoded
+}
+
+impl EncodeSet {
+    ///Encodes only non-ASCII bytes
+    pub const NONE: EncodeSet = EncodeSet { ascii: 0 };
+    ///Every ASCII byte except letters and digits
+    pub const NON_ALPHANUMERIC: EncodeSet = EncodeSet::NONE
+        .add_range(0x00, 0x2F)
+        .add_range(b':', b'@')
+        .add_range(b'[', b'`')
+        .add_range(b'{', 0x7F);
+    ///RFC 3986: everything except unreserved `A-Z a-z 0-9 - . _ ~`
+    pub const UNRESERVED: EncodeSet = EncodeSet::NON_ALPHANUMERIC.remove_all(b"-._~");
+    ///WHATWG C0 control percent-encode set
+    pub const CONTROLS: EncodeSet = EncodeSet::NONE.add_range(0x00, 0x1F).add(0x7F);
+    ///WHATWG fragment percent-encode set
+    pub const FRAGMENT: EncodeSet = EncodeSet::CONTROLS.add_all(b" \"<>`");
+    ///WHATWG query percent-encode set
+    pub const QUERY: EncodeSet = EncodeSet::CONTROLS.add_all(b" \"#<>");
+    ///WHATWG special-query percent-encode set (query set for http, https, ws, wss, ftp, file)
+    pub const SPECIAL_QUERY: EncodeSet = EncodeSet::QUERY.add(b'\'');
+    ///WHATWG path percent-encode set
+    pub const PATH: EncodeSet = EncodeSet::QUERY.add_all(b"?^`{}");
+    ///WHATWG userinfo percent-encode set
+    pub const USERINFO: EncodeSet = EncodeSet::PATH.add_all(b"/:;=@[\\]^|");
+    ///WHATWG component percent-encode set, same as JavaScript's encodeURIComponent
+    pub const COMPONENT: EncodeSet = EncodeSet::USERINFO.add_all(b"$%&+,");
+    ///application/x-www-form-urlencoded percent-encode set
+    pub const FORM: EncodeSet = EncodeSet::COMPONENT.add_all(b"!'()~");
+    ///The set `encoding_percent_component` has always used, keeps `$ - _ . + ! * ( )`
+    pub const LEGACY: EncodeSet = EncodeSet::NON_ALPHANUMERIC.remove_all(b"$-_.+!*()");
+    ///RFC 8187 attr-char, what a header parameter ext-value keeps
+    pub const ATTR_CHAR: EncodeSet = EncodeSet::NON_ALPHANUMERIC.remove_all(b"!#$&+-.^_`|~");
+
+    pub const fn add(self, b: u8) -> EncodeSet {
+        if b < 128 {
+            EncodeSet { ascii: self.ascii | (1 << b) }
+        } else {
+            self
+        }
+    }
+
+    pub const fn remove(self, b: u8) -> EncodeSet {
+        if b < 128 {
+            EncodeSet { ascii: self.ascii & !(1 << b) }
+        } else {
+            self //non-ASCII can not be left raw in a URL
+        }
+    }
+
+    pub const fn add_all(self, bytes: &[u8]) -> EncodeSet {
+        let mut set = self;
+        let mut i = 0;
+        while i < bytes.len() {
+            set = set.add(bytes[i]);
+            i += 1;
+        }
+        set
+    }
+
+    pub const fn remove_all(self, bytes: &[u8]) -> EncodeSet {
+        let mut set = self;
+        let mut i = 0;
+        while i < bytes.len() {
+            set = set.remove(bytes[i]);
+            i += 1;
+        }
+        set
+    }
+
+    const fn add_range(self, start: u8, end: u8) -> EncodeSet {
+        let mut set = self;
+        let mut b = start;
+        while b <= end {
+            set = set.add(b);
+            b += 1;
+        }
+        set
+    }
+
+    pub const fn contains(&self, b: u8) -> bool {
+        b >= 128 || self.ascii & (1 << b) != 0
+    }
+}
+
+impl std::str::FromStr for EncodeSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "legacy" => Ok(EncodeSet::LEGACY),
+            "unreserved" | "rfc3986" => Ok(EncodeSet::UNRESERVED),
+            "alphanumeric" => Ok(EncodeSet::NON_ALPHANUMERIC),
+            "controls" => Ok(EncodeSet::CONTROLS),
+            "fragment" => Ok(EncodeSet::FRAGMENT),
+            "query" => Ok(EncodeSet::QUERY),
+            "special-query" => Ok(EncodeSet::SPECIAL_QUERY),
+            "path" => Ok(EncodeSet::PATH),
+            "userinfo" => Ok(EncodeSet::USERINFO),
+            "component" => Ok(EncodeSet::COMPONENT),
+            "form" => Ok(EncodeSet::FORM),
+            "attr-char" | "rfc8187" => Ok(EncodeSet::ATTR_CHAR),
+            _ => Err(format!(
+                "unknown set '{}' (legacy, unreserved, alphanumeric, controls, fragment, query, special-query, path, userinfo, component, form, attr-char)",
+                s
+            )),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Percent Encoding and Decoding
+
+//ANCHOR - Encode Percent
+pub fn encoding_percent_component(inp: &str) -> String {
+    encoding_percent_set(inp, &EncodeSet::LEGACY)
+}
+
+pub fn encoding_percent_set(inp: &str, set: &EncodeSet) -> String {
+    encoding_percent_bytes(inp.as_bytes(), set)
+}
+
+pub fn encoding_percent_bytes(inp: &[u8], set: &EncodeSet) -> String {
+    let mut buffer = Vec::new();
+    for &i in inp {
+        if set.contains(i) {
+            buffer.push(b'%');
+            buffer.push(binoperate1(i >> 4)); //for getting 4bits high| example pass i>>4 = 3
+            buffer.push(binoperate1(i & 0xF)); //for getting 4bits low| example pass i & 0xF = 2
+        } else {
+            buffer.push(i);
+        }
+    }
+    unsafe { String::from_utf8_unchecked(buffer) }
+}
+
+pub fn binoperate1(digit: u8) -> u8 {
+    let digit = digit & 0xF; //only the low 4 bits make a hex digit
+    match digit {
+        0..=9 => b'0' + digit,   //it start from 48 + ... it return a number
+        _ => b'A' + digit - 10, //start from 65 + ... - 10 it will return a char
+    }
+}
+
+//ANCHOR - Decode Percent
+pub fn decoding_percent(inp: &str) -> Result<String, UrlencodeError> {
+    bytes_to_string(decoding_percent_bytes(inp)?)
+}
+
+pub fn decoding_percent_bytes(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let bytes = inp.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'%' => {
+                if idx + 2 >= bytes.len() {
+                    return Err(UrlencodeError::TruncatedEscape { offset: idx });
+                }
+                let (first, second) = (bytes[idx + 1], bytes[idx + 2]);
+                let v1 = binoperate2(first).ok_or(UrlencodeError::InvalidCharacter { offset: idx + 1, byte: first })?;
+                let v2 = binoperate2(second).ok_or(UrlencodeError::InvalidCharacter { offset: idx + 2, byte: second })?;
+                buffer.push((v1 << 4) | v2);
+                idx += 3;
+            }
+            b => {
+                buffer.push(b);
+                idx += 1;
+            }
+        }
+    }
+    Ok(buffer)
+}
+
+pub fn binoperate2(inp: u8) -> Option<u8> {
+    match inp {
+        b'0'..=b'9' => Some(inp - b'0'),
+        b'A'..=b'F' => Some(inp - b'A' + 10),
+        b'a'..=b'f' => Some(inp - b'a' + 10),
+        _ => None,
+    }
+} //found % it will remove % and next 2 digit
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//application/x-www-form-urlencoded
+
+//ANCHOR - Encode Form
+pub fn encoding_form(inp: &str) -> String {
+    encoding_form_bytes(inp.as_bytes())
+}
+
+pub fn encoding_form_bytes(inp: &[u8]) -> String {
+    encoding_percent_bytes(inp, &EncodeSet::FORM.remove(b' ')).replace(' ', "+")
+}
+
+//ANCHOR - Decode Form
+pub fn decoding_form(inp: &str) -> Result<String, UrlencodeError> {
+    decoding_percent(&inp.replace('+', " "))
+}
+
+pub fn decoding_form_bytes(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    decoding_percent_bytes(&inp.replace('+', " "))
+}
+
+//serialize key/value pairs into a form body, "a=1&b=x+y"
+pub fn encoding_form_pairs<K: AsRef<str>, V: AsRef<str>>(pairs: &[(K, V)]) -> String {
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", encoding_form(k.as_ref()), encoding_form(v.as_ref())))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+///Query parameters in order, a key without '=' has no value
+pub type QueryPairs = Vec<(String, Option<String>)>;
+
+//ANCHOR - Parse Query
+//"?a=1&b=%20x&a=2", a URL or a relative reference into ordered pairs, a key without '=' has no value.
+//The query runs from the first '?' to '#', without a '?' the input is a bare query unless it starts with "scheme://".
+//The byte range of the query in `inp` comes back too, it is empty where a query would go when there is none
+pub fn parse_query(inp: &str) -> Result<(QueryPairs, Range<usize>), UrlencodeError> {
+    let hash = inp.find('#').unwrap_or(inp.len());
+    let range = match inp[..hash].find('?') {
+        Some(idx) => idx + 1..hash,
+        None if inp.find("://").is_some_and(|idx| is_scheme(&inp[..idx])) => hash..hash,
+        None => 0..hash,
+    };
+
+    let mut pairs = Vec::new();
+    let mut start = range.start; //offset of the pair in `inp`, for errors
+    for pair in inp[range.clone()].split('&') {
+        if !pair.is_empty() {
+            match pair.split_once('=') {
+                Some((k, v)) => {
+                    let key = decoding_form(k).map_err(|e| e.offset_by(start))?;
+                    let value = decoding_form(v).map_err(|e| e.offset_by(start + k.len() + 1))?;
+                    pairs.push((key, Some(value)));
+                }
+                None => pairs.push((decoding_form(pair).map_err(|e| e.offset_by(start))?, None)),
+            }
+        }
+        start += pair.len() + 1;
+    }
+    Ok((pairs, range))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryEdit {
+    Set(String, String),
+    Add(String, String),
+    Remove(String),
+    Sort,
+}
+
+impl QueryEdit {
+    pub fn apply(&self, pairs: &mut QueryPairs) {
+        match self {
+            QueryEdit::Set(key, value) => {
+                match pairs.iter().position(|(k, _)| k == key) {
+                    Some(first) => {
+                        pairs[first].1 = Some(value.clone());
+                        let mut idx = 0;
+                        pairs.retain(|(k, _)| {
+                            let keep = k != key || idx == first; //later duplicates are dropped
+                            idx += 1;
+                            keep
+                        });
+                    }
+                    None => pairs.push((key.clone(), Some(value.clone()))),
+                }
+            }
+            QueryEdit::Add(key, value) => pairs.push((key.clone(), Some(value.clone()))),
+            QueryEdit::Remove(key) => pairs.retain(|(k, _)| k != key),
+            QueryEdit::Sort => pairs.sort_by(|a, b| a.0.cmp(&b.0)),
+        }
+    }
+}
+
+//serialize parsed pairs back, each key and value is encoded on its own so '&' and '=' stay separators
+pub fn encoding_query_pairs(pairs: &[(String, Option<String>)]) -> String {
+    pairs
+        .iter()
+        .map(|(k, v)| match v {
+            Some(v) => format!("{}={}", encoding_form(k), encoding_form(v)),
+            None => encoding_form(k),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+//ANCHOR - Edit Query
+//only the query is rewritten, whatever comes before it and the #fragment are kept as they are
+pub fn edit_query(inp: &str, edits: &[QueryEdit]) -> Result<String, UrlencodeError> {
+    let (mut pairs, range) = parse_query(inp)?;
+    for edit in edits {
+        edit.apply(&mut pairs);
+    }
+    let query = encoding_query_pairs(&pairs);
+
+    let mut result = inp[..range.start].to_string();
+    if query.is_empty() {
+        if result.ends_with('?') {
+            result.pop();
+        }
+    } else if !result.is_empty() && !result.ends_with('?') {
+        result.push('?'); //the URL had no query
+    }
+    result.push_str(&query);
+    result.push_str(&inp[range.end..]);
+    Ok(result)
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Whole URL: RFC 3986 components, each encoded with its own allowed characters
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    #[error("Invalid port: {0}")]
+    InvalidPort(String),
+    #[error("Invalid host: {0}")]
+    InvalidHost(String),
+    #[error("Base URL has no scheme: {0}")]
+    RelativeBase(String),
+}
+
+///URL reference split into its RFC 3986 components
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Url {
+    pub scheme: Option<String>,
+    pub userinfo: Option<String>,
+    pub host: Option<String>, //None when there is no authority, Some("") for "file:///..."
+    pub port: Option<u16>,
+    pub path: Vec<String>, //segments split on '/', so "/book/" is ["", "book", ""]
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+//ANCHOR - Parse URL
+impl Url {
+    pub fn parse(inp: &str) -> Result<Url, UrlError> {
+        let mut url = Url::default();
+        let mut rest = inp;
+
+        //fragment and query are cut first because they may hold ':' '/' '@' freely
+        if let Some(idx) = rest.find('#') {
+            url.fragment = Some(rest[idx + 1..].to_string());
+            rest = &rest[..idx];
+        }
+        if let Some(idx) = rest.find('?') {
+            url.query = Some(rest[idx + 1..].to_string());
+            rest = &rest[..idx];
+        }
+
+        if let Some(idx) = rest.find(':') {
+            if is_scheme(&rest[..idx]) {
+                url.scheme = Some(rest[..idx].to_string());
+                rest = &rest[idx + 1..];
+            }
+        }
+
+        if let Some(after) = rest.strip_prefix("//") {
+            let end = after.find('/').unwrap_or(after.len());
+            let authority = &after[..end];
+            rest = &after[end..];
+
+            let hostport = match authority.rfind('@') {
+                Some(at) => {
+                    url.userinfo = Some(authority[..at].to_string());
+                    &authority[at + 1..]
+                }
+                None => authority,
+            };
+            let (host, port) = split_host_port(hostport)?;
+            url.host = Some(host);
+            url.port = port;
+        }
+
+        url.set_path(rest);
+        Ok(url)
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path.join("/")
+    }
+
+    pub fn set_path(&mut self, path: &str) {
+        self.path = if path.is_empty() {
+            Vec::new()
+        } else {
+            path.split('/').map(|seg| seg.to_string()).collect()
+        };
+    }
+
+    //ANCHOR - Encode URL components
+    pub fn encoded(&self) -> Url {
+        Url {
+            scheme: self.scheme.clone(),
+            userinfo: self.userinfo.as_deref().map(|u| encoding_percent_url_part(u, &USERINFO_SET)),
+            host: self.host.as_deref().map(|h| {
+                if h.starts_with('[') {
+                    h.to_string() //IP literal is kept as it is
+                } else {
+                    encoding_percent_url_part(h, &REGNAME_SET)
+                }
+            }),
+            port: self.port,
+            path: self.path.iter().map(|seg| encoding_percent_url_part(seg, &PCHAR_SET)).collect(),
+            query: self.query.as_deref().map(|q| encoding_percent_url_part(q, &URL_QUERY_SET)),
+            fragment: self.fragment.as_deref().map(|fr| encoding_percent_url_part(fr, &URL_QUERY_SET)),
+        }
+    }
+}
+
+impl std::fmt::Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(host) = &self.host {
+            write!(f, "//")?;
+            if let Some(userinfo) = &self.userinfo {
+                write!(f, "{}@", userinfo)?;
+            }
+            write!(f, "{}", host)?;
+            if let Some(port) = self.port {
+                write!(f, ":{}", port)?;
+            }
+            if self.path.first().is_some_and(|seg| !seg.is_empty()) {
+                write!(f, "/")?; //path after an authority must start with '/'
+            }
+        }
+        write!(f, "{}", self.path_string())?;
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+fn split_host_port(inp: &str) -> Result<(String, Option<u16>), UrlError> {
+    let (host, port) = if inp.starts_with('[') {
+        let close = inp.find(']').ok_or_else(|| UrlError::InvalidHost(inp.to_string()))?;
+        match &inp[close + 1..] {
+            "" => (&inp[..=close], None),
+            after => match after.strip_prefix(':') {
+                Some(p) => (&inp[..=close], Some(p)),
+                None => return Err(UrlError::InvalidHost(inp.to_string())),
+            },
+        }
+    } else {
+        match inp.rfind(':') {
+            Some(idx) => (&inp[..idx], Some(&inp[idx + 1..])),
+            None => (inp, None),
+        }
+    };
+
+    let port = match port {
+        None | Some("") => None,
+        Some(p) if p.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(p.parse().map_err(|_| UrlError::InvalidPort(p.to_string()))?)
+        }
+        Some(p) => return Err(UrlError::InvalidPort(p.to_string())),
+    };
+    Ok((host.to_string(), port))
+}
+
+//scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+fn is_scheme(inp: &str) -> bool {
+    let mut bytes = inp.bytes();
+    matches!(bytes.next(), Some(b) if b.is_ascii_alphabetic())
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+}
+
+//RFC 3986 component sets, valid escapes are kept by encoding_percent_url_part
+const REGNAME_SET: EncodeSet = EncodeSet::UNRESERVED.remove_all(b"!$&'()*+,;=");
+const USERINFO_SET: EncodeSet = REGNAME_SET.remove(b':');
+const PCHAR_SET: EncodeSet = USERINFO_SET.remove(b'@');
+const URL_QUERY_SET: EncodeSet = PCHAR_SET.remove_all(b"/?");
+
+//percent-encode every byte in `set`, escapes that are already valid (%XX) are left alone
+fn encoding_percent_url_part(inp: &str, set: &EncodeSet) -> String {
+    let bytes = inp.as_bytes();
+    let mut buffer = String::new();
+    for (idx, &i) in bytes.iter().enumerate() {
+        let escaped = i == b'%'
+            && bytes.get(idx + 1).and_then(|&b| binoperate2(b)).is_some()
+            && bytes.get(idx + 2).and_then(|&b| binoperate2(b)).is_some();
+        if !set.contains(i) || escaped {
+            buffer.push(i as char);
+        } else {
+            buffer.push('%');
+            buffer.push(binoperate1(i >> 4) as char);
+            buffer.push(binoperate1(i & 0xF) as char);
+        }
+    }
+    buffer
+}
+
+//ANCHOR - Encode whole URL
+pub fn encoding_percent_url(inp: &str) -> Result<String, UrlError> {
+    Ok(Url::parse(inp)?.ascii_host()?.encoded().to_string())
+}
+
+//ANCHOR - Decode whole URL
+//every component is percent-decoded and the host is shown in Unicode, meant for reading rather than reuse
+pub fn decoding_percent_url(inp: &str) -> Result<String, UrlencodeError> {
+    let url = Url::parse(inp)?;
+    let decoded = Url {
+        scheme: url.scheme.clone(),
+        userinfo: url.userinfo.as_deref().map(decoding_percent).transpose()?,
+        host: match url.host.as_deref() {
+            Some(h) if h.starts_with('[') => Some(h.to_string()),
+            Some(h) => Some(domain_to_unicode(&decoding_percent(h)?)),
+            None => None,
+        },
+        port: url.port,
+        path: url.path.iter().map(|seg| decoding_percent(seg)).collect::<Result<_, _>>()?,
+        query: url.query.as_deref().map(decoding_percent).transpose()?,
+        fragment: url.fragment.as_deref().map(decoding_percent).transpose()?,
+    };
+    Ok(decoded.to_string())
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//IDNA and Punycode (RFC 3492)
+
+//ANCHOR - IDNA hosts
+impl Url {
+    ///The same URL with an internationalized host in its xn-- form, ASCII hosts and IP literals are kept as they are
+    pub fn ascii_host(&self) -> Result<Url, UrlError> {
+        let mut url = self.clone();
+        if let Some(h) = &self.host {
+            //a percent-encoded UTF-8 host is an internationalized host as well
+            let host = match decoding_percent(h) {
+                Ok(decoded) if !decoded.is_ascii() => decoded,
+                _ => h.clone(),
+            };
+            if !host.is_ascii() {
+                url.host = Some(domain_to_ascii(&host)?);
+            }
+        }
+        Ok(url)
+    }
+}
+
+//label separators UTS #46 maps to '.'
+fn is_label_dot(c: char) -> bool {
+    matches!(c, '.' | '\u{3002}' | '\u{ff0e}' | '\u{ff61}')
+}
+
+///IDNA ToASCII: lowercase the domain and write every non-ASCII label as xn-- Punycode.
+///Only the case mapping of UTS #46 is done, there is no NFC normalization.
+pub fn domain_to_ascii(inp: &str) -> Result<String, UrlError> {
+    let mapped: String = inp.chars().map(|c| if is_label_dot(c) { '.' } else { c }).flat_map(char::to_lowercase).collect();
+    let mut labels = Vec::new();
+    for label in mapped.split('.') {
+        let label = if label.is_ascii() {
+            //an xn-- label has to be valid Punycode already
+            if let Some(code) = label.strip_prefix("xn--") {
+                frompunycode(code).map_err(|_| UrlError::InvalidHost(inp.to_string()))?;
+            }
+            label.to_string()
+        } else {
+            format!("xn--{}", topunycode(label))
+        };
+        if label.len() > 63 {
+            return Err(UrlError::InvalidHost(inp.to_string()));
+        }
+        labels.push(label);
+    }
+    let domain = labels.join(".");
+    if domain.trim_end_matches('.').len() > 253 {
+        return Err(UrlError::InvalidHost(inp.to_string()));
+    }
+    Ok(domain)
+}
+
+///IDNA ToUnicode: every xn-- label is decoded, a label that is not valid Punycode is kept as it is
+pub fn domain_to_unicode(inp: &str) -> String {
+    inp.split(is_label_dot)
+        .map(|label| match label.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("xn--") => frompunycode(&label[4..]).unwrap_or_else(|_| label.to_string()),
+            _ => label.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+//RFC 3492 section 5 parameters
+const PUNY_BASE: u64 = 36;
+const PUNY_TMIN: u64 = 1;
+const PUNY_TMAX: u64 = 26;
+const PUNY_SKEW: u64 = 38;
+const PUNY_DAMP: u64 = 700;
+const PUNY_INITIAL_BIAS: u64 = 72;
+const PUNY_INITIAL_N: u64 = 128;
+
+//RFC 3492 section 6.1
+fn punyadapt(delta: u64, numpoints: u64, firsttime: bool) -> u64 {
+    let mut delta = if firsttime { delta / PUNY_DAMP } else { delta / 2 };
+    delta += delta / numpoints;
+    let mut k = 0;
+    while delta > ((PUNY_BASE - PUNY_TMIN) * PUNY_TMAX) / 2 {
+        delta /= PUNY_BASE - PUNY_TMIN;
+        k += PUNY_BASE;
+    }
+    k + (PUNY_BASE - PUNY_TMIN + 1) * delta / (delta + PUNY_SKEW)
+}
+
+//the threshold of digit position k
+fn punythreshold(k: u64, bias: u64) -> u64 {
+    if k <= bias {
+        PUNY_TMIN
+    } else if k >= bias + PUNY_TMAX {
+        PUNY_TMAX
+    } else {
+        k - bias
+    }
+}
+
+//0-25 are 'a'-'z', 26-35 are '0'-'9'
+fn punydigit(digit: u64) -> char {
+    match digit {
+        0..=25 => (b'a' + digit as u8) as char,
+        _ => (b'0' + (digit - 26) as u8) as char,
+    }
+}
+
+fn punyvalue(inp: u8) -> Option<u64> {
+    match inp {
+        b'a'..=b'z' => Some((inp - b'a') as u64),
+        b'A'..=b'Z' => Some((inp - b'A') as u64),
+        b'0'..=b'9' => Some((inp - b'0') as u64 + 26),
+        _ => None,
+    }
+}
+
+//ANCHOR - Encode Punycode
+//one label without the xn-- prefix, the basic code points come first and stay as they are
+pub fn topunycode(inp: &str) -> String {
+    let input: Vec<u64> = inp.chars().map(|c| c as u64).collect();
+    let mut output: String = inp.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u64;
+    let mut handled = basic;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = PUNY_INITIAL_N;
+    let mut delta: u64 = 0;
+    let mut bias = PUNY_INITIAL_BIAS;
+    while handled < input.len() as u64 {
+        let m = input.iter().copied().filter(|&c| c >= n).min().unwrap_or(n);
+        delta += (m - n) * (handled + 1);
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta += 1;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = PUNY_BASE;
+                loop {
+                    let t = punythreshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(punydigit(t + (q - t) % (PUNY_BASE - t)));
+                    q = (q - t) / (PUNY_BASE - t);
+                    k += PUNY_BASE;
+                }
+                output.push(punydigit(q));
+                bias = punyadapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+    output
+}
+
+//ANCHOR - Decode Punycode
+//one label without the xn-- prefix
+pub fn frompunycode(inp: &str) -> Result<String, UrlencodeError> {
+    let bytes = inp.as_bytes();
+    let (mut output, start): (Vec<char>, usize) = match inp.rfind('-') {
+        Some(idx) => (inp[..idx].chars().collect(), idx + 1),
+        None => (Vec::new(), 0),
+    };
+    if let Some(offset) = output.iter().position(|c| !c.is_ascii()) {
+        return Err(UrlencodeError::InvalidPunycode { offset });
+    }
+
+    let mut n = PUNY_INITIAL_N;
+    let mut i: u64 = 0;
+    let mut bias = PUNY_INITIAL_BIAS;
+    let mut offset = start;
+    while offset < bytes.len() {
+        let oldi = i;
+        let mut w: u64 = 1;
+        let mut k = PUNY_BASE;
+        loop {
+            let byte = *bytes.get(offset).ok_or(UrlencodeError::InvalidPunycode { offset })?;
+            let digit = punyvalue(byte).ok_or(UrlencodeError::InvalidCharacter { offset, byte })?;
+            offset += 1;
+            i = digit.checked_mul(w).and_then(|d| i.checked_add(d)).ok_or(UrlencodeError::InvalidPunycode { offset: offset - 1 })?;
+            let t = punythreshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(PUNY_BASE - t).ok_or(UrlencodeError::InvalidPunycode { offset: offset - 1 })?;
+            k += PUNY_BASE;
+        }
+        let len = output.len() as u64 + 1;
+        bias = punyadapt(i - oldi, len, oldi == 0);
+        n = n.checked_add(i / len).ok_or(UrlencodeError::InvalidPunycode { offset: offset - 1 })?;
+        i %= len;
+        let c = u32::try_from(n).ok().and_then(char::from_u32).ok_or(UrlencodeError::InvalidPunycode { offset: offset - 1 })?;
+        output.insert(i as usize, c);
+        i += 1;
+    }
+    Ok(output.into_iter().collect())
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//URL normalization (RFC 3986 section 6)
+
+///Which normalizations to apply, every step is on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    pub case: bool,         //lowercase scheme and host
+    pub hex_case: bool,     //uppercase hex digits in escapes
+    pub unreserved: bool,   //decode escapes of unreserved characters
+    pub dot_segments: bool, //remove "." and ".." segments
+    pub default_port: bool, //drop the port when it is the scheme default
+    pub empty_path: bool,   //"http://a" becomes "http://a/"
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            case: true,
+            hex_case: true,
+            unreserved: true,
+            dot_segments: true,
+            default_port: true,
+            empty_path: true,
+        }
+    }
+}
+
+//ANCHOR - Normalize URL
+impl Url {
+    pub fn normalized(&self, options: &NormalizeOptions) -> Url {
+        let mut url = self.clone();
+        let escapes = |inp: &str| normalize_escapes(inp, options.hex_case, options.unreserved);
+
+        url.userinfo = url.userinfo.as_deref().map(escapes);
+        url.host = url.host.as_deref().map(escapes);
+        //after the escapes so a decoded letter is lowercased too, escaping again keeps the hex digits uppercase
+        if options.case {
+            url.scheme = url.scheme.map(|scheme| scheme.to_ascii_lowercase());
+            url.host = url.host.map(|host| escapes(&host.to_ascii_lowercase()));
+        }
+        url.path = url.path.iter().map(|seg| escapes(seg)).collect();
+        url.query = url.query.as_deref().map(escapes);
+        url.fragment = url.fragment.as_deref().map(escapes);
+
+        if options.dot_segments {
+            let path = remove_dot_segments(&url.path_string());
+            url.set_path(&path);
+        }
+        if options.default_port && url.port.is_some() && url.port == default_port(url.scheme.as_deref()) {
+            url.port = None;
+        }
+        if options.empty_path && url.host.is_some() && url.path.is_empty() {
+            url.set_path("/");
+        }
+        url
+    }
+}
+
+pub fn normalize_url(inp: &str, options: &NormalizeOptions) -> Result<String, UrlError> {
+    Ok(Url::parse(inp)?.normalized(options).to_string())
+}
+
+pub fn default_port(scheme: Option<&str>) -> Option<u16> {
+    match scheme?.to_ascii_lowercase().as_str() {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+//uppercase the hex digits of every escape and decode the ones that stand for unreserved characters
+fn normalize_escapes(inp: &str, hex_case: bool, unreserved: bool) -> String {
+    let bytes = inp.as_bytes();
+    let mut buffer = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = match (bytes[i], bytes.get(i + 1), bytes.get(i + 2)) {
+            (b'%', Some(&h), Some(&l)) => binoperate2(h).zip(binoperate2(l)),
+            _ => None,
+        };
+        match escape {
+            Some((high, low)) => {
+                let b = (high << 4) | low;
+                if unreserved && !EncodeSet::UNRESERVED.contains(b) {
+                    buffer.push(b);
+                } else if hex_case {
+                    buffer.extend([b'%', binoperate1(high), binoperate1(low)]);
+                } else {
+                    buffer.extend(&bytes[i..i + 3]);
+                }
+                i += 3;
+            }
+            None => {
+                buffer.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(buffer).expect("only ASCII escapes are decoded")
+}
+
+//ANCHOR - Remove dot segments (RFC 3986 section 5.2.4)
+pub fn remove_dot_segments(path: &str) -> String {
+    let mut input = path.to_string();
+    let mut output = String::new();
+    let pop_segment = |output: &mut String| match output.rfind('/') {
+        Some(idx) => output.truncate(idx),
+        None => output.clear(),
+    };
+
+    while !input.is_empty() {
+        if input.starts_with("../") {
+            input.drain(..3);
+        } else if input.starts_with("./") {
+            input.drain(..2);
+        } else if input.starts_with("/./") {
+            input.replace_range(..3, "/");
+        } else if input == "/." {
+            input = "/".to_string();
+        } else if input.starts_with("/../") {
+            input.replace_range(..4, "/");
+            pop_segment(&mut output);
+        } else if input == "/.." {
+            input = "/".to_string();
+            pop_segment(&mut output);
+        } else if input == "." || input == ".." {
+            input.clear();
+        } else {
+            //move the first segment, with its leading '/', to the output
+            //the first character is skipped whole, it is not always one byte
+            let end = match input.char_indices().skip(1).find(|(_, c)| *c == '/') {
+                Some((idx, _)) => idx,
+                None => input.len(),
+            };
+            output.push_str(&input[..end]);
+            input.drain(..end);
+        }
+    }
+    output
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Reference resolution (RFC 3986 section 5.2)
+
+//ANCHOR - Resolve reference
+impl Url {
+    pub fn resolve(&self, reference: &Url) -> Result<Url, UrlError> {
+        if self.scheme.is_none() {
+            return Err(UrlError::RelativeBase(self.to_string()));
+        }
+        let mut target = reference.clone();
+
+        if reference.scheme.is_none() {
+            if reference.host.is_none() {
+                let ref_path = reference.path_string();
+                if ref_path.is_empty() {
+                    target.path = self.path.clone();
+                    if reference.query.is_none() {
+                        target.query = self.query.clone();
+                    }
+                } else if ref_path.starts_with('/') {
+                    target.set_path(&remove_dot_segments(&ref_path));
+                } else {
+                    target.set_path(&remove_dot_segments(&self.merge(&ref_path)));
+                }
+                target.userinfo = self.userinfo.clone();
+                target.host = self.host.clone();
+                target.port = self.port;
+                target.scheme = self.scheme.clone();
+                return Ok(target);
+            }
+            target.scheme = self.scheme.clone();
+        }
+        target.set_path(&remove_dot_segments(&reference.path_string()));
+        Ok(target)
+    }
+
+    //RFC 3986 section 5.2.3
+    fn merge(&self, ref_path: &str) -> String {
+        let base_path = self.path_string();
+        if self.host.is_some() && base_path.is_empty() {
+            format!("/{}", ref_path)
+        } else {
+            match base_path.rfind('/') {
+                Some(idx) => format!("{}{}", &base_path[..=idx], ref_path),
+                None => ref_path.to_string(),
+            }
+        }
+    }
+}
+
+pub fn resolve(base: &str, reference: &str) -> Result<String, UrlError> {
+    Ok(Url::parse(base)?.resolve(&Url::parse(reference)?)?.to_string())
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Base64 Misc: Matching, trait, etc.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base64Alphabet {
+    Standard, //'+' and '/'
+    UrlSafe,  //'-' and '_' (base64url, RFC 4648 section 5)
+}
+
+fn matchingb64(inp: u8, alphabet: Base64Alphabet) -> u8 {
+    match (inp, alphabet) {
+        (0..=25, _) => b'A' + inp,
+        (26..=51, _) => b'a' + (inp - 26),
+        (52..=61, _) => b'0' + (inp - 52),
+        (62, Base64Alphabet::Standard) => b'+',
+        (63, Base64Alphabet::Standard) => b'/',
+        (62, Base64Alphabet::UrlSafe) => b'-',
+        (63, Base64Alphabet::UrlSafe) => b'_',
+        _ => panic!("Invalid"),
+    }
+}
+
+//accepts both alphabets
+fn matchingb642(inp: u8) -> Option<u8> {
+    match inp {
+        b'A'..=b'Z' => Some(inp - b'A'),
+        b'a'..=b'z' => Some(inp - b'a' + 26),
+        b'0'..=b'9' => Some(inp - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Base64 Encoding and Decoding
+
+//ANCHOR - Encode Base64
+pub fn tobase64(inp: &str) -> String {
+    tobase64_with(inp.as_bytes(), Base64Alphabet::Standard, true)
+}
+
+pub fn tobase64_with(inp: &[u8], alphabet: Base64Alphabet, pad: bool) -> String {
+    let iter: Vec<_> = inp.chunks(3).collect(); //type: [[u8,u8,u8]] (3 bytes)
+    let mut result = Vec::new();
+    let mut checking: u8 = 0;
+    for i in iter {
+        if i.len() == 2 {
+            checking = 2;
+        } else if i.len() == 1 {
+            checking = 1;
+        }
+
+        let a = binoperate3(i).unwrap();
+        for j in a {
+            result.push(matchingb64(j, alphabet));
+        }
+    }
+
+    let returnstring = String::from_utf8(result).expect("Invalid");
+    if !pad {
+        return returnstring;
+    }
+    match checking {
+        1 => format!("{}==", returnstring),
+        2 => format!("{}=", returnstring),
+        _ => returnstring,
+    }
+}
+
+pub fn binoperate3(inp: &[u8]) -> Result<Vec<u8>, UrlencodeError> {
+    //make it to binary format
+    match inp.len() {
+        3 => {
+            let res = format!("{:08b}{:08b}{:08b}", inp[0], inp[1], inp[2]);
+            let newbase64 = res
+                .as_bytes()
+                .chunks(6)
+                .map(str::from_utf8)
+                .collect::<Result<Vec<&str>, _>>()
+                .unwrap();
+            let newbase64s: Vec<u8> = newbase64
+                .iter()
+                .map(|&b| u8::from_str_radix(b, 2).unwrap())
+                .collect();
+            Ok(newbase64s)
+        }
+        2 => {
+            let res = format!("{:08b}{:08b}", inp[0], inp[1]);
+            let mut newbase64 = res
+                .as_bytes()
+                .chunks(6)
+                .map(str::from_utf8)
+                .collect::<Result<Vec<&str>, _>>()
+                .unwrap();
+            let mut adjust = newbase64[newbase64.len() - 1].to_string();
+
+            if let Some(last_element) = newbase64.last_mut() {
+                while adjust.len() < 6 {
+                    adjust.push('0');
+                }
+                *last_element = &adjust;
+            }
+
+            let newbase64s: Vec<u8> = newbase64
+                .iter()
+                .map(|&b| u8::from_str_radix(b, 2).unwrap())
+                .collect();
+            Ok(newbase64s)
+        }
+        1 => {
+            let res = format!("{:08b}", inp[0]);
+            let mut newbase64 = res
+                .as_bytes()
+                .chunks(6)
+                .map(str::from_utf8)
+                .collect::<Result<Vec<&str>, _>>()
+                .unwrap();
+            let mut adjust = newbase64[newbase64.len() - 1].to_string();
+
+            if let Some(last_element) = newbase64.last_mut() {
+                while adjust.len() < 6 {
+                    adjust.push('0');
+                }
+                *last_element = &adjust;
+            }
+
+            let newbase64s: Vec<u8> = newbase64
+                .iter()
+                .map(|&b| u8::from_str_radix(b, 2).unwrap())
+                .collect();
+            Ok(newbase64s)
+        }
+        n => Err(UrlencodeError::InvalidLength(n)),
+    }
+}
+
+//ANCHOR - Decode Base64
+//standard or url-safe alphabet, padding is optional
+pub fn frombase64(inp: &str) -> Result<String, UrlencodeError> {
+    bytes_to_string(frombase64_bytes(inp)?)
+}
+
+//line breaks and other whitespace inside the input are skipped
+pub fn frombase64_bytes(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    let mut buffer = Vec::new();
+    let mut padding = 0;
+    let mut pad_start = 0;
+    for (offset, &i) in inp.as_bytes().iter().enumerate() {
+        if i.is_ascii_whitespace() {
+            continue;
+        }
+        if i == b'=' {
+            if padding == 0 {
+                pad_start = offset;
+            }
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err(UrlencodeError::BadPadding { offset: pad_start }); //data after '='
+        }
+        let rev1 = matchingb642(i).ok_or(UrlencodeError::InvalidCharacter { offset, byte: i })?;
+        buffer.push(rev1);
+    }
+
+    //a lone 6-bit value can not make a byte, and padding has to fill the last group of 4
+    if buffer.len() % 4 == 1 || padding > 2 || (padding > 0 && (buffer.len() + padding) % 4 != 0) {
+        return Err(UrlencodeError::BadPadding { offset: if padding > 0 { pad_start } else { inp.len() } });
+    }
+
+    let mut decoded_bytes = Vec::new();
+    for i in 0..buffer.len().saturating_sub(1) {
+        match i % 4 {
+            0 => decoded_bytes.push((buffer[i] << 2) | (buffer[i + 1] >> 4)),
+            1 => decoded_bytes.push(((buffer[i] & 0b1111) << 4) | (buffer[i + 1] >> 2)),
+            2 => decoded_bytes.push(((buffer[i] & 0b11) << 6) | buffer[i + 1]),
+            _ => (),
+        }
+    }
+    Ok(decoded_bytes)
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//HTML character references
+
+///Where the escaped text goes, a quoted attribute value also needs its quotes escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlContext {
+    Text,
+    Attribute,
+}
+
+impl std::str::FromStr for HtmlContext {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(HtmlContext::Text),
+            "attribute" | "attr" => Ok(HtmlContext::Attribute),
+            _ => Err(format!("unknown HTML context '{}' (text, attribute)", s)),
+        }
+    }
+}
+
+//HTML 4 named character references and &apos;, sorted by name for binary search
+const HTML_ENTITIES: [(&str, char); 253] = [
+    ("AElig", '\u{c6}'), ("Aacute", '\u{c1}'), ("Acirc", '\u{c2}'), ("Agrave", '\u{c0}'), ("Alpha", '\u{391}'), ("Aring", '\u{c5}'),
+    ("Atilde", '\u{c3}'), ("Auml", '\u{c4}'), ("Beta", '\u{392}'), ("Ccedil", '\u{c7}'), ("Chi", '\u{3a7}'), ("Dagger", '\u{2021}'),
+    ("Delta", '\u{394}'), ("ETH", '\u{d0}'), ("Eacute", '\u{c9}'), ("Ecirc", '\u{ca}'), ("Egrave", '\u{c8}'), ("Epsilon", '\u{395}'),
+    ("Eta", '\u{397}'), ("Euml", '\u{cb}'), ("Gamma", '\u{393}'), ("Iacute", '\u{cd}'), ("Icirc", '\u{ce}'), ("Igrave", '\u{cc}'),
+    ("Iota", '\u{399}'), ("Iuml", '\u{cf}'), ("Kappa", '\u{39a}'), ("Lambda", '\u{39b}'), ("Mu", '\u{39c}'), ("Ntilde", '\u{d1}'),
+    ("Nu", '\u{39d}'), ("OElig", '\u{152}'), ("Oacute", '\u{d3}'), ("Ocirc", '\u{d4}'), ("Ograve", '\u{d2}'), ("Omega", '\u{3a9}'),
+    ("Omicron", '\u{39f}'), ("Oslash", '\u{d8}'), ("Otilde", '\u{d5}'), ("Ouml", '\u{d6}'), ("Phi", '\u{3a6}'), ("Pi", '\u{3a0}'),
+    ("Prime", '\u{2033}'), ("Psi", '\u{3a8}'), ("Rho", '\u{3a1}'), ("Scaron", '\u{160}'), ("Sigma", '\u{3a3}'), ("THORN", '\u{de}'),
+    ("Tau", '\u{3a4}'), ("Theta", '\u{398}'), ("Uacute", '\u{da}'), ("Ucirc", '\u{db}'), ("Ugrave", '\u{d9}'), ("Upsilon", '\u{3a5}'),
+    ("Uuml", '\u{dc}'), ("Xi", '\u{39e}'), ("Yacute", '\u{dd}'), ("Yuml", '\u{178}'), ("Zeta", '\u{396}'), ("aacute", '\u{e1}'),
+    ("acirc", '\u{e2}'), ("acute", '\u{b4}'), ("aelig", '\u{e6}'), ("agrave", '\u{e0}'), ("alefsym", '\u{2135}'), ("alpha", '\u{3b1}'),
+    ("amp", '&'), ("and", '\u{2227}'), ("ang", '\u{2220}'), ("apos", '\''), ("aring", '\u{e5}'), ("asymp", '\u{2248}'),
+    ("atilde", '\u{e3}'), ("auml", '\u{e4}'), ("bdquo", '\u{201e}'), ("beta", '\u{3b2}'), ("brvbar", '\u{a6}'), ("bull", '\u{2022}'),
+    ("cap", '\u{2229}'), ("ccedil", '\u{e7}'), ("cedil", '\u{b8}'), ("cent", '\u{a2}'), ("chi", '\u{3c7}'), ("circ", '\u{2c6}'),
+    ("clubs", '\u{2663}'), ("cong", '\u{2245}'), ("copy", '\u{a9}'), ("crarr", '\u{21b5}'), ("cup", '\u{222a}'), ("curren", '\u{a4}'),
+    ("dArr", '\u{21d3}'), ("dagger", '\u{2020}'), ("darr", '\u{2193}'), ("deg", '\u{b0}'), ("delta", '\u{3b4}'), ("diams", '\u{2666}'),
+    ("divide", '\u{f7}'), ("eacute", '\u{e9}'), ("ecirc", '\u{ea}'), ("egrave", '\u{e8}'), ("empty", '\u{2205}'), ("emsp", '\u{2003}'),
+    ("ensp", '\u{2002}'), ("epsilon", '\u{3b5}'), ("equiv", '\u{2261}'), ("eta", '\u{3b7}'), ("eth", '\u{f0}'), ("euml", '\u{eb}'),
+    ("euro", '\u{20ac}'), ("exist", '\u{2203}'), ("fnof", '\u{192}'), ("forall", '\u{2200}'), ("frac12", '\u{bd}'), ("frac14", '\u{bc}'),
+    ("frac34", '\u{be}'), ("frasl", '\u{2044}'), ("gamma", '\u{3b3}'), ("ge", '\u{2265}'), ("gt", '>'), ("hArr", '\u{21d4}'),
+    ("harr", '\u{2194}'), ("hearts", '\u{2665}'), ("hellip", '\u{2026}'), ("iacute", '\u{ed}'), ("icirc", '\u{ee}'), ("iexcl", '\u{a1}'),
+    ("igrave", '\u{ec}'), ("image", '\u{2111}'), ("infin", '\u{221e}'), ("int", '\u{222b}'), ("iota", '\u{3b9}'), ("iquest", '\u{bf}'),
+    ("isin", '\u{2208}'), ("iuml", '\u{ef}'), ("kappa", '\u{3ba}'), ("lArr", '\u{21d0}'), ("lambda", '\u{3bb}'), ("lang", '\u{2329}'),
+    ("laquo", '\u{ab}'), ("larr", '\u{2190}'), ("lceil", '\u{2308}'), ("ldquo", '\u{201c}'), ("le", '\u{2264}'), ("lfloor", '\u{230a}'),
+    ("lowast", '\u{2217}'), ("loz", '\u{25ca}'), ("lrm", '\u{200e}'), ("lsaquo", '\u{2039}'), ("lsquo", '\u{2018}'), ("lt", '<'),
+    ("macr", '\u{af}'), ("mdash", '\u{2014}'), ("micro", '\u{b5}'), ("middot", '\u{b7}'), ("minus", '\u{2212}'), ("mu", '\u{3bc}'),
+    ("nabla", '\u{2207}'), ("nbsp", '\u{a0}'), ("ndash", '\u{2013}'), ("ne", '\u{2260}'), ("ni", '\u{220b}'), ("not", '\u{ac}'),
+    ("notin", '\u{2209}'), ("nsub", '\u{2284}'), ("ntilde", '\u{f1}'), ("nu", '\u{3bd}'), ("oacute", '\u{f3}'), ("ocirc", '\u{f4}'),
+    ("oelig", '\u{153}'), ("ograve", '\u{f2}'), ("oline", '\u{203e}'), ("omega", '\u{3c9}'), ("omicron", '\u{3bf}'), ("oplus", '\u{2295}'),
+    ("or", '\u{2228}'), ("ordf", '\u{aa}'), ("ordm", '\u{ba}'), ("oslash", '\u{f8}'), ("otilde", '\u{f5}'), ("otimes", '\u{2297}'),
+    ("ouml", '\u{f6}'), ("para", '\u{b6}'), ("part", '\u{2202}'), ("permil", '\u{2030}'), ("perp", '\u{22a5}'), ("phi", '\u{3c6}'),
+    ("pi", '\u{3c0}'), ("piv", '\u{3d6}'), ("plusmn", '\u{b1}'), ("pound", '\u{a3}'), ("prime", '\u{2032}'), ("prod", '\u{220f}'),
+    ("prop", '\u{221d}'), ("psi", '\u{3c8}'), ("quot", '"'), ("rArr", '\u{21d2}'), ("radic", '\u{221a}'), ("rang", '\u{232a}'),
+    ("raquo", '\u{bb}'), ("rarr", '\u{2192}'), ("rceil", '\u{2309}'), ("rdquo", '\u{201d}'), ("real", '\u{211c}'), ("reg", '\u{ae}'),
+    ("rfloor", '\u{230b}'), ("rho", '\u{3c1}'), ("rlm", '\u{200f}'), ("rsaquo", '\u{203a}'), ("rsquo", '\u{2019}'), ("sbquo", '\u{201a}'),
+    ("scaron", '\u{161}'), ("sdot", '\u{22c5}'), ("sect", '\u{a7}'), ("shy", '\u{ad}'), ("sigma", '\u{3c3}'), ("sigmaf", '\u{3c2}'),
+    ("sim", '\u{223c}'), ("spades", '\u{2660}'), ("sub", '\u{2282}'), ("sube", '\u{2286}'), ("sum", '\u{2211}'), ("sup", '\u{2283}'),
+    ("sup1", '\u{b9}'), ("sup2", '\u{b2}'), ("sup3", '\u{b3}'), ("supe", '\u{2287}'), ("szlig", '\u{df}'), ("tau", '\u{3c4}'),
+    ("there4", '\u{2234}'), ("theta", '\u{3b8}'), ("thetasym", '\u{3d1}'), ("thinsp", '\u{2009}'), ("thorn", '\u{fe}'), ("tilde", '\u{2dc}'),
+    ("times", '\u{d7}'), ("trade", '\u{2122}'), ("uArr", '\u{21d1}'), ("uacute", '\u{fa}'), ("uarr", '\u{2191}'), ("ucirc", '\u{fb}'),
+    ("ugrave", '\u{f9}'), ("uml", '\u{a8}'), ("upsih", '\u{3d2}'), ("upsilon", '\u{3c5}'), ("uuml", '\u{fc}'), ("weierp", '\u{2118}'),
+    ("xi", '\u{3be}'), ("yacute", '\u{fd}'), ("yen", '\u{a5}'), ("yuml", '\u{ff}'), ("zeta", '\u{3b6}'), ("zwj", '\u{200d}'),
+    ("zwnj", '\u{200c}'),
+];
+
+//ANCHOR - Encode HTML
+//text escapes & < >, attribute escapes & " ' so the value is safe in either kind of quotes
+pub fn encoding_html(inp: &str, context: HtmlContext) -> String {
+    let mut buffer = String::new();
+    for c in inp.chars() {
+        match (c, context) {
+            ('&', _) => buffer.push_str("&amp;"),
+            ('<', HtmlContext::Text) => buffer.push_str("&lt;"),
+            ('>', HtmlContext::Text) => buffer.push_str("&gt;"),
+            ('"', HtmlContext::Attribute) => buffer.push_str("&quot;"),
+            ('\'', HtmlContext::Attribute) => buffer.push_str("&#39;"),
+            (c, _) => buffer.push(c),
+        }
+    }
+    buffer
+}
+
+//ANCHOR - Decode HTML
+//named, decimal and hex references ending in ';', anything else is kept as it is written
+pub fn decoding_html(inp: &str) -> String {
+    let mut buffer = String::new();
+    let mut rest = inp;
+    while let Some(start) = rest.find('&') {
+        buffer.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end > 1 && end <= 32).and_then(|end| {
+            let name = &rest[1..end];
+            let c = match name.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => htmlnumber(&hex[1..], 16),
+                Some(dec) => htmlnumber(dec, 10),
+                None => HTML_ENTITIES.binary_search_by(|(entity, _)| entity.cmp(&name)).ok().map(|idx| HTML_ENTITIES[idx].1),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                buffer.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                buffer.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    buffer.push_str(rest);
+    buffer
+}
+
+//at least one digit and nothing else, a number too big for u32 is past U+10FFFF as well
+fn htmlnumber(digits: &str, radix: u32) -> Option<char> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None; //"&#;" and "&#x+41;" stay as they are
+    }
+    Some(u32::from_str_radix(digits, radix).map_or('\u{fffd}', htmlcodepoint))
+}
+
+//NUL, surrogates and values past U+10FFFF become U+FFFD like in a browser
+fn htmlcodepoint(inp: u32) -> char {
+    match inp {
+        0 => '\u{fffd}',
+        _ => char::from_u32(inp).unwrap_or('\u{fffd}'),
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Quoted-printable (RFC 2045 section 6.7) and MIME encoded-words (RFC 2047)
+
+//an encoded line holds at most 76 characters, the '=' of a soft line break included
+const QP_LINE: usize = 76;
+//an encoded-word is at most 75 characters long
+const MIME_WORD: usize = 75;
+
+//ANCHOR - Encode Quoted-printable
+//'\n' or "\r\n" is a hard line break, other control bytes, '=' and non-ASCII become =XX,
+//binary data has no line breaks of its own so CR and LF are =0D and =0A (rule 4)
+pub fn toquotedprintable(inp: &[u8], binary: bool) -> String {
+    let mut result = String::new();
+    let mut line = 0;
+    let mut idx = 0;
+    while idx < inp.len() {
+        let i = inp[idx];
+        let hardbreak = if binary {
+            None
+        } else if i == b'\n' {
+            Some(1)
+        } else if i == b'\r' && inp.get(idx + 1) == Some(&b'\n') {
+            Some(2)
+        } else {
+            None
+        };
+        if let Some(len) = hardbreak {
+            result.push_str("\r\n");
+            line = 0;
+            idx += len;
+            continue;
+        }
+
+        //space and tab are kept, except right before a line break where they would be stripped
+        let at_end = matches!(inp.get(idx + 1), None | Some(b'\n') | Some(b'\r'));
+        let literal = matches!(i, 33..=60 | 62..=126) || (matches!(i, b' ' | b'\t') && !at_end);
+        let token = if literal {
+            (i as char).to_string()
+        } else {
+            format!("={}{}", binoperate1(i >> 4) as char, binoperate1(i & 0xF) as char)
+        };
+        if line + token.len() > QP_LINE - 1 {
+            result.push_str("=\r\n");
+            line = 0;
+        }
+        result.push_str(&token);
+        line += token.len();
+        idx += 1;
+    }
+    result
+}
+
+//ANCHOR - Decode Quoted-printable
+//a '=' at the end of a line or of the input is a soft line break, the whitespace that ends a line is dropped,
+//any other line break is a hard one and comes out as CRLF (binary data has none, its CR and LF are escaped)
+pub fn fromquotedprintable(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    let mut decoded_bytes = Vec::new();
+    let mut offset = 0;
+    let mut lines = inp.split('\n').peekable();
+    while let Some(raw) = lines.next() {
+        let line = raw.trim_end_matches(['\r', ' ', '\t']);
+        let (line, soft) = match line.strip_suffix('=') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let bytes = line.as_bytes();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            if bytes[idx] == b'=' {
+                let hex = bytes.get(idx + 1..idx + 3).ok_or(UrlencodeError::TruncatedEscape { offset: offset + idx })?;
+                let high = binoperate2(hex[0]).ok_or(UrlencodeError::InvalidCharacter { offset: offset + idx + 1, byte: hex[0] })?;
+                let low = binoperate2(hex[1]).ok_or(UrlencodeError::InvalidCharacter { offset: offset + idx + 2, byte: hex[1] })?;
+                decoded_bytes.push(high << 4 | low);
+                idx += 3;
+            } else {
+                decoded_bytes.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+        if !soft && lines.peek().is_some() {
+            decoded_bytes.extend_from_slice(b"\r\n");
+        }
+        offset += raw.len() + 1;
+    }
+    Ok(decoded_bytes)
+}
+
+//ANCHOR - Encode MIME words
+//the Q encoding of one byte inside an encoded-word, '_' stands for a space
+fn mimeq(i: u8) -> String {
+    match i {
+        b' ' => "_".to_string(),
+        b'!' | b'*' | b'+' | b'-' | b'/' => (i as char).to_string(),
+        _ if i.is_ascii_alphanumeric() => (i as char).to_string(),
+        _ => format!("={}{}", binoperate1(i >> 4) as char, binoperate1(i & 0xF) as char),
+    }
+}
+
+///UTF-8 encoded-words, Q when it comes out shorter than B, folded onto new lines when one word is not enough.
+///A word never splits a character.
+pub fn encoding_mime_words(inp: &str) -> String {
+    let qlen: usize = inp.bytes().map(|i| mimeq(i).len()).sum();
+    let q = qlen <= inp.len().div_ceil(3) * 4;
+    let prefix = if q { "=?UTF-8?Q?" } else { "=?UTF-8?B?" };
+    let room = MIME_WORD - prefix.len() - 2;
+
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in inp.chars() {
+        let mut next = chunk.clone();
+        next.push(c);
+        let len = if q { next.bytes().map(|i| mimeq(i).len()).sum() } else { next.len().div_ceil(3) * 4 };
+        if len > room && !chunk.is_empty() {
+            words.push(chunk);
+            chunk = c.to_string();
+        } else {
+            chunk = next;
+        }
+    }
+    if !chunk.is_empty() || words.is_empty() {
+        words.push(chunk);
+    }
+
+    words
+        .iter()
+        .map(|word| {
+            let text = if q { word.bytes().map(mimeq).collect() } else { tobase64(word) };
+            format!("{}{}?=", prefix, text)
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n ")
+}
+
+//ANCHOR - Decode MIME words
+///Decode every encoded-word, whitespace between two of them is dropped and any other text is kept
+pub fn decoding_mime_words(inp: &str) -> Result<String, UrlencodeError> {
+    let mut result = String::new();
+    let mut pending: Option<(String, Vec<u8>)> = None; //charset and bytes of adjacent words
+    let mut gap = String::new(); //text since the last word
+    let mut rest = inp;
+    let mut offset = 0;
+    while let Some(start) = rest.find("=?") {
+        let word = mimeword(&rest[start..]);
+        let Some((charset, bytes, len)) = word else {
+            gap.push_str(&rest[..start + 2]);
+            offset += start + 2;
+            rest = &rest[start + 2..];
+            continue;
+        };
+        gap.push_str(&rest[..start]);
+        let bytes = bytes.map_err(|e| e.offset_by(offset + start))?;
+
+        //linear whitespace between adjacent words is not part of the text
+        match pending.take() {
+            Some((prev, mut data)) if gap.trim().is_empty() && prev == charset => {
+                data.extend(bytes);
+                pending = Some((prev, data));
+            }
+            Some((prev, data)) => {
+                result.push_str(&mimecharset(&prev, data)?);
+                if !gap.trim().is_empty() {
+                    result.push_str(&gap);
+                }
+                pending = Some((charset, bytes));
+            }
+            None => {
+                result.push_str(&gap);
+                pending = Some((charset, bytes));
+            }
+        }
+        gap.clear();
+        offset += start + len;
+        rest = &rest[start + len..];
+    }
+    if let Some((charset, data)) = pending {
+        result.push_str(&mimecharset(&charset, data)?);
+    }
+    result.push_str(&gap);
+    result.push_str(rest);
+    Ok(result)
+}
+
+//"=?charset?B|Q?text?=" at the start of `inp`: the lowercase charset, the decoded bytes and the length of the word
+fn mimeword(inp: &str) -> Option<(String, Result<Vec<u8>, UrlencodeError>, usize)> {
+    let mut parts = inp[2..].splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let tail = parts.next()?;
+    let end = tail.find("?=")?;
+    let text = &tail[..end];
+    if charset.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    let textstart = len - end - 2;
+    let bytes = match encoding {
+        "B" | "b" => frombase64_bytes(text),
+        "Q" | "q" => frommimeq(text),
+        _ => return None,
+    };
+    //RFC 2231 lets a language follow the charset after '*'
+    let charset = charset.split('*').next().unwrap_or(charset).to_ascii_lowercase();
+    Some((charset, bytes.map_err(|e| e.offset_by(textstart)), len))
+}
+
+//the Q encoding: '_' is a space and =XX a byte, there are no line breaks inside a word
+fn frommimeq(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    let bytes = inp.as_bytes();
+    let mut decoded_bytes = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'_' => decoded_bytes.push(b' '),
+            b'=' => {
+                let hex = bytes.get(idx + 1..idx + 3).ok_or(UrlencodeError::TruncatedEscape { offset: idx })?;
+                let high = binoperate2(hex[0]).ok_or(UrlencodeError::InvalidCharacter { offset: idx + 1, byte: hex[0] })?;
+                let low = binoperate2(hex[1]).ok_or(UrlencodeError::InvalidCharacter { offset: idx + 2, byte: hex[1] })?;
+                decoded_bytes.push(high << 4 | low);
+                idx += 2;
+            }
+            i => decoded_bytes.push(i),
+        }
+        idx += 1;
+    }
+    Ok(decoded_bytes)
+}
+
+//bytes of a word to text
+fn mimecharset(charset: &str, bytes: Vec<u8>) -> Result<String, UrlencodeError> {
+    match charset {
+        "utf-8" | "utf8" => bytes_to_string(bytes),
+        "us-ascii" | "ascii" => match bytes.iter().position(|b| !b.is_ascii()) {
+            Some(offset) => Err(UrlencodeError::InvalidCharacter { offset, byte: bytes[offset] }),
+            None => bytes_to_string(bytes),
+        },
+        "iso-8859-1" | "latin1" | "latin-1" => Ok(bytes.iter().map(|&b| b as char).collect()),
+        _ => Err(UrlencodeError::UnsupportedCharset(charset.to_string())),
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//HTTP header parameters (RFC 8187, formerly RFC 5987)
+
+//ANCHOR - Encode header parameter
+///charset'language'value with everything outside attr-char percent-encoded, the charset is always UTF-8
+pub fn encoding_ext_value(inp: &str, language: &str) -> String {
+    format!("UTF-8'{}'{}", language, encoding_percent_bytes(inp.as_bytes(), &EncodeSet::ATTR_CHAR))
+}
+
+///`name="fallback"; name*=UTF-8'language'value`, the fallback is ASCII for clients without RFC 8187
+pub fn encoding_header_param(name: &str, inp: &str, language: &str) -> String {
+    format!("{}=\"{}\"; {}*={}", name, asciifallback(inp), name, encoding_ext_value(inp, language))
+}
+
+//a quoted-string close to the value: Latin-1 letters lose their accents, other non-ASCII and controls become '_'
+fn asciifallback(inp: &str) -> String {
+    let mut buffer = String::new();
+    for c in inp.chars() {
+        match c {
+            '"' | '\\' => {
+                buffer.push('\\');
+                buffer.push(c);
+            }
+            ' '..='~' => buffer.push(c),
+            'À'..='Å' => buffer.push('A'),
+            'à'..='å' => buffer.push('a'),
+            'Æ' => buffer.push_str("AE"),
+            'æ' => buffer.push_str("ae"),
+            'Ç' => buffer.push('C'),
+            'ç' => buffer.push('c'),
+            'È'..='Ë' => buffer.push('E'),
+            'è'..='ë' => buffer.push('e'),
+            'Ì'..='Ï' => buffer.push('I'),
+            'ì'..='ï' => buffer.push('i'),
+            'Ð' => buffer.push('D'),
+            'ð' => buffer.push('d'),
+            'Ñ' => buffer.push('N'),
+            'ñ' => buffer.push('n'),
+            'Ò'..='Ö' | 'Ø' => buffer.push('O'),
+            'ò'..='ö' | 'ø' => buffer.push('o'),
+            'Ù'..='Ü' => buffer.push('U'),
+            'ù'..='ü' => buffer.push('u'),
+            'Ý' => buffer.push('Y'),
+            'ý' | 'ÿ' => buffer.push('y'),
+            'Þ' => buffer.push_str("TH"),
+            'þ' => buffer.push_str("th"),
+            'ß' => buffer.push_str("ss"),
+            _ => buffer.push('_'),
+        }
+    }
+    buffer
+}
+
+//ANCHOR - Decode header parameter
+///charset'language'value to (charset, language, value), UTF-8 and ISO-8859-1 are understood
+pub fn decoding_ext_value(inp: &str) -> Result<(String, String, String), UrlencodeError> {
+    let mut parts = inp.trim().splitn(3, '\'');
+    let (Some(charset), Some(language), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(UrlencodeError::InvalidExtValue(inp.to_string()));
+    };
+    let bytes = decoding_percent_bytes(value).map_err(|e| e.offset_by(charset.len() + language.len() + 2))?;
+    let decoded = mimecharset(&charset.to_ascii_lowercase(), bytes)?;
+    Ok((charset.to_string(), language.to_string(), decoded))
+}
+
+///Every name=value parameter of a header value, lowercase names, a name ending in '*' holds the decoded ext-value.
+///A leading "Header-Name:" and the disposition type are skipped.
+pub fn parse_header_params(inp: &str) -> Result<Vec<(String, String)>, UrlencodeError> {
+    let mut params = Vec::new();
+    for segment in splitunquoted(inp, ';') {
+        let Some((name, value)) = segment.split_once('=') else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        let value = if name.ends_with('*') {
+            decoding_ext_value(value)?.2
+        } else {
+            unquote(value.trim())
+        };
+        params.push((name, value));
+    }
+    Ok(params)
+}
+
+///The value of `name*`, or of `name` when there is no extended form
+pub fn decoding_header_param(inp: &str, name: &str) -> Result<String, UrlencodeError> {
+    let params = parse_header_params(inp)?;
+    let name = name.to_ascii_lowercase();
+    let extended = format!("{}*", name);
+    params
+        .iter()
+        .find(|(k, _)| *k == extended)
+        .or_else(|| params.iter().find(|(k, _)| *k == name))
+        .map(|(_, v)| v.clone())
+        .ok_or(UrlencodeError::MissingParameter(name))
+}
+
+//split on `sep` outside of double quotes, a backslash escapes inside them
+fn splitunquoted(inp: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, c) in inp.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(&inp[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&inp[start..]);
+    parts
+}
+
+//a quoted-string without its quotes and escapes, a token is returned as it is
+fn unquote(inp: &str) -> String {
+    match inp.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        Some(inner) => {
+            let mut buffer = String::new();
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => buffer.extend(chars.next()),
+                    c => buffer.push(c),
+                }
+            }
+            buffer
+        }
+        None => inp.to_string(),
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Base85 Encoding and Decoding (btoa/Adobe Ascii85, RFC 1924, Z85)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base85Variant {
+    Ascii85, //btoa, 'z' for four zero bytes
+    Adobe,   //Ascii85 inside '<~' and '~>'
+    Rfc1924,
+    Z85,
+}
+
+impl Base85Variant {
+    fn chars(&self) -> &'static [u8; 85] {
+        match self {
+            Base85Variant::Ascii85 | Base85Variant::Adobe => b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstu",
+            Base85Variant::Rfc1924 => b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~",
+            Base85Variant::Z85 => b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#",
+        }
+    }
+
+    fn is_ascii85(&self) -> bool {
+        matches!(self, Base85Variant::Ascii85 | Base85Variant::Adobe)
+    }
+}
+
+impl std::str::FromStr for Base85Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ascii85" | "btoa" => Ok(Base85Variant::Ascii85),
+            "adobe" | "adobe85" => Ok(Base85Variant::Adobe),
+            "rfc1924" | "base85" => Ok(Base85Variant::Rfc1924),
+            "z85" => Ok(Base85Variant::Z85),
+            _ => Err(format!("unknown Base85 variant '{}' (adobe, ascii85, rfc1924, z85)", s)),
+        }
+    }
+}
+
+//ANCHOR - Encode Base85
+//every 4 bytes are a big-endian number written as 5 digits, a short last group of n bytes is
+//padded with zeros and gives n + 1 digits (Z85 itself only defines whole groups)
+pub fn tobase85(inp: &[u8], variant: Base85Variant) -> String {
+    let chars = variant.chars();
+    let mut result = String::new();
+    if variant == Base85Variant::Adobe {
+        result.push_str("<~");
+    }
+    for group in inp.chunks(4) {
+        if group == [0, 0, 0, 0] && variant.is_ascii85() {
+            result.push('z');
+            continue;
+        }
+        let mut padded = [0u8; 4];
+        padded[..group.len()].copy_from_slice(group);
+        let mut value = u32::from_be_bytes(padded);
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = chars[(value % 85) as usize];
+            value /= 85;
+        }
+        for &digit in &digits[..group.len() + 1] {
+            result.push(digit as char);
+        }
+    }
+    if variant == Base85Variant::Adobe {
+        result.push_str("~>");
+    }
+    result
+}
+
+//ANCHOR - Decode Base85
+//whitespace is skipped, Ascii85 and Adobe both take the input with or without '<~ ~>'
+pub fn frombase85(inp: &str, variant: Base85Variant) -> Result<Vec<u8>, UrlencodeError> {
+    let chars = variant.chars();
+    let mut start = 0;
+    let mut end = inp.len();
+    if variant.is_ascii85() {
+        let trimmed = inp.trim_end();
+        if trimmed.ends_with("~>") {
+            end = trimmed.len() - 2;
+        }
+        let leading = inp.len() - inp.trim_start().len();
+        if inp[leading..end].starts_with("<~") {
+            start = leading + 2;
+        }
+    }
+
+    let mut decoded_bytes = Vec::new();
+    let mut group: Vec<(usize, u8)> = Vec::new(); //digit and where it was
+    for (offset, &i) in inp.as_bytes()[..end].iter().enumerate().skip(start) {
+        if i.is_ascii_whitespace() {
+            continue;
+        }
+        if i == b'z' && variant.is_ascii85() {
+            if !group.is_empty() {
+                return Err(UrlencodeError::InvalidCharacter { offset, byte: i }); //'z' inside a group
+            }
+            decoded_bytes.extend_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        let digit = chars.iter().position(|&c| c == i).ok_or(UrlencodeError::InvalidCharacter { offset, byte: i })?;
+        group.push((offset, digit as u8));
+        if group.len() == 5 {
+            decoded_bytes.extend_from_slice(&base85group(inp.as_bytes(), &group, 4)?);
+            group.clear();
+        }
+    }
+
+    //a lone digit can not make a byte, the others are padded with the highest digit
+    match group.len() {
+        0 => (),
+        1 => return Err(UrlencodeError::BadPadding { offset: group[0].0 }),
+        n => {
+            let last = group[n - 1].0;
+            group.resize(5, (last, 84));
+            decoded_bytes.extend_from_slice(&base85group(inp.as_bytes(), &group, n - 1)?);
+        }
+    }
+    Ok(decoded_bytes)
+}
+
+//5 digits back to 4 bytes, only the first `keep` are used
+fn base85group(inp: &[u8], group: &[(usize, u8)], keep: usize) -> Result<Vec<u8>, UrlencodeError> {
+    let mut value: u64 = 0;
+    for &(_, digit) in group {
+        value = value * 85 + digit as u64;
+    }
+    if value > u32::MAX as u64 {
+        let (offset, _) = group[0];
+        return Err(UrlencodeError::InvalidCharacter { offset, byte: inp[offset] }); //the group does not fit in 32 bits
+    }
+    Ok((value as u32).to_be_bytes()[..keep].to_vec())
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Base58 Encoding and Decoding
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base58Alphabet {
+    Bitcoin,
+    Flickr,
+}
+
+impl Base58Alphabet {
+    fn chars(&self) -> &'static [u8; 58] {
+        match self {
+            Base58Alphabet::Bitcoin => b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz",
+            Base58Alphabet::Flickr => b"123456789abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ",
+        }
+    }
+}
+
+impl std::str::FromStr for Base58Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bitcoin" | "btc" => Ok(Base58Alphabet::Bitcoin),
+            "flickr" => Ok(Base58Alphabet::Flickr),
+            _ => Err(format!("unknown Base58 alphabet '{}' (bitcoin, flickr)", s)),
+        }
+    }
+}
+
+//ANCHOR - Encode Base58
+//every leading zero byte becomes the first symbol, the rest is one big number in base 58
+pub fn tobase58(inp: &[u8], alphabet: Base58Alphabet) -> String {
+    let chars = alphabet.chars();
+    let zeros = inp.iter().take_while(|&&b| b == 0).count();
+    let mut digits: Vec<u8> = Vec::new(); //base 58, least significant first
+    for &i in &inp[zeros..] {
+        let mut carry = i as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut result = String::new();
+    for _ in 0..zeros {
+        result.push(chars[0] as char);
+    }
+    for &digit in digits.iter().rev() {
+        result.push(chars[digit as usize] as char);
+    }
+    result
+}
+
+//ANCHOR - Decode Base58
+//whitespace is skipped
+pub fn frombase58(inp: &str, alphabet: Base58Alphabet) -> Result<Vec<u8>, UrlencodeError> {
+    let chars = alphabet.chars();
+    let mut zeros = 0;
+    let mut leading = true;
+    let mut bytes: Vec<u8> = Vec::new(); //base 256, least significant first
+    for (offset, &i) in inp.as_bytes().iter().enumerate() {
+        if i.is_ascii_whitespace() {
+            continue;
+        }
+        let value = chars.iter().position(|&c| c == i).ok_or(UrlencodeError::InvalidCharacter { offset, byte: i })?;
+        if leading && value == 0 {
+            zeros += 1;
+            continue;
+        }
+        leading = false;
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut decoded_bytes = vec![0; zeros];
+    decoded_bytes.extend(bytes.iter().rev());
+    Ok(decoded_bytes)
+}
+
+//ANCHOR - Base58Check
+//the data (version byte and payload) followed by the first 4 bytes of its double SHA-256
+pub fn tobase58check(inp: &[u8], alphabet: Base58Alphabet) -> String {
+    let checksum = sha256(&sha256(inp));
+    tobase58(&[inp, &checksum[..4]].concat(), alphabet)
+}
+
+//returns the version byte and payload once the checksum matches
+pub fn frombase58check(inp: &str, alphabet: Base58Alphabet) -> Result<Vec<u8>, UrlencodeError> {
+    let mut data = frombase58(inp, alphabet)?;
+    if data.len() < 5 {
+        return Err(UrlencodeError::MissingChecksum(data.len()));
+    }
+    let found = data.split_off(data.len() - 4);
+    let expected = sha256(&sha256(&data));
+    if found[..] != expected[..4] {
+        return Err(UrlencodeError::ChecksumMismatch {
+            expected: u32::from_be_bytes([expected[0], expected[1], expected[2], expected[3]]),
+            found: u32::from_be_bytes([found[0], found[1], found[2], found[3]]),
+        });
+    }
+    Ok(data)
+}
+
+//ANCHOR - SHA-256 (FIPS 180-4), for the Base58Check checksum
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub fn sha256(inp: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    //pad with 0x80, zeros and the bit length so the message fills whole 64-byte blocks
+    let mut message = inp.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((inp.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (t, word) in block.chunks(4).enumerate() {
+            w[t] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for t in 16..64 {
+            let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+            let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+            w[t] = w[t - 16].wrapping_add(s0).wrapping_add(w[t - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for t in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[t]).wrapping_add(w[t]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, state) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&state.to_be_bytes());
+    }
+    digest
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Hex Encoding and Decoding
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexSeparator {
+    None,
+    Colon,
+    Space,
+    Escape, //"\x" before every byte
+}
+
+impl std::str::FromStr for HexSeparator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(HexSeparator::None),
+            "colon" | ":" => Ok(HexSeparator::Colon),
+            "space" | " " => Ok(HexSeparator::Space),
+            "escape" | "\\x" => Ok(HexSeparator::Escape),
+            _ => Err(format!("unknown separator '{}' (none, colon, space, escape)", s)),
+        }
+    }
+}
+
+//ANCHOR - Encode Hex
+pub fn tohex(inp: &[u8], upper: bool, separator: HexSeparator) -> String {
+    let mut buffer = String::new();
+    for (idx, &i) in inp.iter().enumerate() {
+        match separator {
+            HexSeparator::Colon if idx > 0 => buffer.push(':'),
+            HexSeparator::Space if idx > 0 => buffer.push(' '),
+            HexSeparator::Escape => buffer.push_str("\\x"),
+            _ => (),
+        }
+        for digit in [binoperate1(i >> 4), binoperate1(i & 0xF)] {
+            buffer.push(if upper { digit } else { digit.to_ascii_lowercase() } as char);
+        }
+    }
+    buffer
+}
+
+//ANCHOR - Decode Hex
+//either case, whitespace, ':' and '\x' between the bytes are skipped, a line break of a wrapped payload anywhere
+pub fn fromhex(inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+    let bytes = inp.as_bytes();
+    let mut decoded_bytes = Vec::new();
+    let mut high: Option<(usize, u8)> = None; //first digit of a byte and where it was
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let i = bytes[offset];
+        if i == b'\n' || i == b'\r' || (high.is_none() && (i.is_ascii_whitespace() || i == b':')) {
+            offset += 1;
+            continue;
+        }
+        if high.is_none() && i == b'\\' && bytes.get(offset + 1) == Some(&b'x') {
+            offset += 2;
+            continue;
+        }
+        let digit = binoperate2(i).ok_or(UrlencodeError::InvalidCharacter { offset, byte: i })?;
+        match high.take() {
+            Some((_, h)) => decoded_bytes.push(h << 4 | digit),
+            None => high = Some((offset, digit)),
+        }
+        offset += 1;
+    }
+    match high {
+        Some((offset, _)) => Err(UrlencodeError::TruncatedEscape { offset }), //odd number of digits
+        None => Ok(decoded_bytes),
+    }
+}
+
+//ANCHOR - Hexdump
+//xxd layout: offset, 16 bytes in groups of 2, then the printable ASCII with '.' for the rest
+pub fn hexdump(inp: &[u8]) -> String {
+    let mut lines = Vec::new();
+    for (idx, chunk) in inp.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for (n, pair) in chunk.chunks(2).enumerate() {
+            if n > 0 {
+                hex.push(' ');
+            }
+            hex.push_str(&tohex(pair, false, HexSeparator::None));
+        }
+        let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        lines.push(format!("{:08x}: {:<39}  {}", idx * 16, hex, ascii));
+    }
+    lines.join("\n")
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Base32 Encoding and Decoding (RFC 4648 section 6 and 7, Crockford)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base32Alphabet {
+    Standard,
+    Hex,
+    Crockford,
+}
+
+impl Base32Alphabet {
+    fn chars(&self) -> &'static [u8; 32] {
+        match self {
+            Base32Alphabet::Standard => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567",
+            Base32Alphabet::Hex => b"0123456789ABCDEFGHIJKLMNOPQRSTUV",
+            Base32Alphabet::Crockford => b"0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+        }
+    }
+}
+
+impl std::str::FromStr for Base32Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" | "base32" | "rfc4648" => Ok(Base32Alphabet::Standard),
+            "hex" | "base32hex" => Ok(Base32Alphabet::Hex),
+            "crockford" => Ok(Base32Alphabet::Crockford),
+            _ => Err(format!("unknown Base32 alphabet '{}' (standard, hex, crockford)", s)),
+        }
+    }
+}
+
+//case-insensitive, Crockford also reads O as 0 and I, L as 1
+fn matchingb32(inp: u8, alphabet: Base32Alphabet) -> Option<u8> {
+    let inp = inp.to_ascii_uppercase();
+    let inp = match (inp, alphabet) {
+        (b'O', Base32Alphabet::Crockford) => b'0',
+        (b'I' | b'L', Base32Alphabet::Crockford) => b'1',
+        _ => inp,
+    };
+    alphabet.chars().iter().position(|&c| c == inp).map(|idx| idx as u8)
+}
+
+//ANCHOR - Encode Base32
+pub fn tobase32(inp: &[u8], alphabet: Base32Alphabet, pad: bool) -> String {
+    let chars = alphabet.chars();
+    let mut result = String::new();
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for &i in inp {
+        bits = (bits << 8) | i as u32;
+        count += 8;
+        while count >= 5 {
+            count -= 5;
+            result.push(chars[((bits >> count) & 31) as usize] as char);
+        }
+        bits &= (1 << count) - 1; //only the bits not written yet
+    }
+    if count > 0 {
+        result.push(chars[((bits << (5 - count)) & 31) as usize] as char);
+    }
+    if pad {
+        while !result.len().is_multiple_of(8) {
+            result.push('=');
+        }
+    }
+    result
+}
+
+//ANCHOR - Decode Base32
+//whitespace is skipped, Crockford also skips '-' used to group symbols
+pub fn frombase32(inp: &str, alphabet: Base32Alphabet) -> Result<Vec<u8>, UrlencodeError> {
+    let mut buffer = Vec::new();
+    let mut padding = 0;
+    let mut pad_start = 0;
+    for (offset, &i) in inp.as_bytes().iter().enumerate() {
+        if i.is_ascii_whitespace() || (i == b'-' && alphabet == Base32Alphabet::Crockford) {
+            continue;
+        }
+        if i == b'=' {
+            if padding == 0 {
+                pad_start = offset;
+            }
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err(UrlencodeError::BadPadding { offset: pad_start }); //data after '='
+        }
+        buffer.push(matchingb32(i, alphabet).ok_or(UrlencodeError::InvalidCharacter { offset, byte: i })?);
+    }
+
+    //1, 3 or 6 symbols left over can not make whole bytes, and padding has to fill the last group of 8
+    if matches!(buffer.len() % 8, 1 | 3 | 6) || padding > 6 || (padding > 0 && (buffer.len() + padding) % 8 != 0) {
+        return Err(UrlencodeError::BadPadding { offset: if padding > 0 { pad_start } else { inp.len() } });
+    }
+
+    let mut decoded_bytes = Vec::new();
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for i in buffer {
+        bits = (bits << 5) | i as u32;
+        count += 5;
+        if count >= 8 {
+            count -= 8;
+            decoded_bytes.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Ok(decoded_bytes)
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Codecs
+
+///A byte encoding the CLI can look up by name, implement it to plug a new one in.
+///A decoder of your own reports its failures as `UrlencodeError::Codec`
+pub trait Codec {
+    fn name(&self) -> &str;
+    fn encode(&self, inp: &[u8]) -> String;
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError>;
+}
+
+///Percent-Encoding with the given set, decoding ignores the set
+pub struct PercentCodec {
+    pub set: EncodeSet,
+}
+
+impl Codec for PercentCodec {
+    fn name(&self) -> &str {
+        "percent"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        encoding_percent_bytes(inp, &self.set)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        decoding_percent_bytes(inp)
+    }
+}
+
+///application/x-www-form-urlencoded
+pub struct FormCodec;
+
+impl Codec for FormCodec {
+    fn name(&self) -> &str {
+        "form"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        encoding_form_bytes(inp)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        decoding_form_bytes(inp)
+    }
+}
+
+///Base64, decoding takes both alphabets with or without padding
+pub struct Base64Codec {
+    pub alphabet: Base64Alphabet,
+    pub pad: bool,
+}
+
+impl Codec for Base64Codec {
+    fn name(&self) -> &str {
+        match self.alphabet {
+            Base64Alphabet::Standard => "base64",
+            Base64Alphabet::UrlSafe => "base64url",
+        }
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        tobase64_with(inp, self.alphabet, self.pad)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        frombase64_bytes(inp)
+    }
+}
+
+///Base32, decoding takes missing padding, lowercase and whitespace
+pub struct Base32Codec {
+    pub alphabet: Base32Alphabet,
+    pub pad: bool,
+}
+
+impl Codec for Base32Codec {
+    fn name(&self) -> &str {
+        match self.alphabet {
+            Base32Alphabet::Standard => "base32",
+            Base32Alphabet::Hex => "base32hex",
+            Base32Alphabet::Crockford => "crockford",
+        }
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        tobase32(inp, self.alphabet, self.pad)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        frombase32(inp, self.alphabet)
+    }
+}
+
+///Hex (Base16), decoding skips whitespace, ':' and '\x'
+pub struct HexCodec {
+    pub upper: bool,
+    pub separator: HexSeparator,
+}
+
+impl Codec for HexCodec {
+    fn name(&self) -> &str {
+        "hex"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        tohex(inp, self.upper, self.separator)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        fromhex(inp)
+    }
+}
+
+///Base58 without a checksum
+pub struct Base58Codec {
+    pub alphabet: Base58Alphabet,
+}
+
+impl Codec for Base58Codec {
+    fn name(&self) -> &str {
+        match self.alphabet {
+            Base58Alphabet::Bitcoin => "base58",
+            Base58Alphabet::Flickr => "base58flickr",
+        }
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        tobase58(inp, self.alphabet)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        frombase58(inp, self.alphabet)
+    }
+}
+
+///Base58Check, without a version byte the first byte of the data is taken as it
+pub struct Base58CheckCodec {
+    pub alphabet: Base58Alphabet,
+    pub version: Option<u8>,
+}
+
+impl Codec for Base58CheckCodec {
+    fn name(&self) -> &str {
+        "base58check"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        match self.version {
+            Some(version) => tobase58check(&[&[version], inp].concat(), self.alphabet),
+            None => tobase58check(inp, self.alphabet),
+        }
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        let data = frombase58check(inp, self.alphabet)?;
+        match (self.version, data.first()) {
+            (Some(expected), Some(&found)) if expected != found => Err(UrlencodeError::VersionMismatch { expected, found }),
+            (Some(_), _) => Ok(data[1..].to_vec()),
+            (None, _) => Ok(data),
+        }
+    }
+}
+
+///Ascii85, Adobe Ascii85, RFC 1924 or Z85
+pub struct Base85Codec {
+    pub variant: Base85Variant,
+}
+
+impl Codec for Base85Codec {
+    fn name(&self) -> &str {
+        match self.variant {
+            Base85Variant::Ascii85 => "ascii85",
+            Base85Variant::Adobe => "adobe85",
+            Base85Variant::Rfc1924 => "rfc1924",
+            Base85Variant::Z85 => "z85",
+        }
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        tobase85(inp, self.variant)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        frombase85(inp, self.variant)
+    }
+}
+
+///RFC 3492 Punycode of one label, without the xn-- prefix
+pub struct PunycodeCodec;
+
+impl Codec for PunycodeCodec {
+    fn name(&self) -> &str {
+        "punycode"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        topunycode(&String::from_utf8_lossy(inp))
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        Ok(frompunycode(inp)?.into_bytes())
+    }
+}
+
+///HTML character references, encoding escapes what the context needs
+pub struct HtmlCodec {
+    pub context: HtmlContext,
+}
+
+impl Codec for HtmlCodec {
+    fn name(&self) -> &str {
+        match self.context {
+            HtmlContext::Text => "html",
+            HtmlContext::Attribute => "html-attr",
+        }
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        encoding_html(&String::from_utf8_lossy(inp), self.context)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        Ok(decoding_html(inp).into_bytes())
+    }
+}
+
+///Quoted-printable, a line break in the data is a hard line break unless the data is binary and it is escaped
+pub struct QuotedPrintableCodec {
+    pub binary: bool,
+}
+
+impl Codec for QuotedPrintableCodec {
+    fn name(&self) -> &str {
+        "quoted-printable"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        toquotedprintable(inp, self.binary)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        fromquotedprintable(inp)
+    }
+}
+
+///RFC 2047 encoded-words in UTF-8
+pub struct MimeWordCodec;
+
+impl Codec for MimeWordCodec {
+    fn name(&self) -> &str {
+        "mime"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        encoding_mime_words(&String::from_utf8_lossy(inp))
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        Ok(decoding_mime_words(inp)?.into_bytes())
+    }
+}
+
+///One HTTP header parameter with its RFC 8187 form, decoding also takes a bare ext-value
+pub struct HeaderParamCodec {
+    pub name: String,
+    pub language: String,
+}
+
+impl Codec for HeaderParamCodec {
+    fn name(&self) -> &str {
+        "header-param"
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        encoding_header_param(&self.name, &String::from_utf8_lossy(inp), &self.language)
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        if inp.contains('=') {
+            Ok(decoding_header_param(inp, &self.name)?.into_bytes())
+        } else {
+            Ok(decoding_ext_value(inp)?.2.into_bytes()) //an ext-value never holds '='
+        }
+    }
+}
+
+//ANCHOR - Codec registry
+///Codecs by name, `default()` holds the built-in ones and `register` adds more
+#[derive(Clone)]
+pub struct CodecRegistry {
+    codecs: Vec<Arc<dyn Codec>>,
+}
+
+impl CodecRegistry {
+    ///An empty registry
+    pub fn new() -> Self {
+        CodecRegistry { codecs: Vec::new() }
+    }
+
+    ///Add a codec, it takes the place of a registered codec with the same name
+    pub fn register<C: Codec + 'static>(&mut self, codec: C) {
+        let codec: Arc<dyn Codec> = Arc::new(codec);
+        match self.codecs.iter().position(|c| c.name().eq_ignore_ascii_case(codec.name())) {
+            Some(idx) => self.codecs[idx] = codec,
+            None => self.codecs.push(codec),
+        }
+    }
+
+    ///Look a codec up by name, case-insensitive
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Codec>, UrlencodeError> {
+        match self.codecs.iter().find(|c| c.name().eq_ignore_ascii_case(name)) {
+            Some(codec) => Ok(Arc::clone(codec)),
+            None => Err(UrlencodeError::UnknownCodec { name: name.to_string(), known: self.names().join(", ") }),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.codecs.iter().map(|c| c.name()).collect()
+    }
+}
+
+impl CodecRegistry {
+    ///Build a chain from names in encoding order, separated by ',' or '|'
+    pub fn chain(&self, spec: &str) -> Result<CodecChain, UrlencodeError> {
+        let codecs = spec
+            .split([',', '|'])
+            .map(|name| self.get(name.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CodecChain::new(codecs))
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        let mut codecs = CodecRegistry::new();
+        codecs.register(PercentCodec { set: EncodeSet::LEGACY });
+        codecs.register(FormCodec);
+        codecs.register(Base64Codec { alphabet: Base64Alphabet::Standard, pad: true });
+        codecs.register(Base64Codec { alphabet: Base64Alphabet::UrlSafe, pad: true });
+        codecs.register(HexCodec { upper: false, separator: HexSeparator::None });
+        codecs.register(Base58Codec { alphabet: Base58Alphabet::Bitcoin });
+        codecs.register(Base58Codec { alphabet: Base58Alphabet::Flickr });
+        codecs.register(Base58CheckCodec { alphabet: Base58Alphabet::Bitcoin, version: None });
+        codecs.register(Base85Codec { variant: Base85Variant::Ascii85 });
+        codecs.register(Base85Codec { variant: Base85Variant::Adobe });
+        codecs.register(Base85Codec { variant: Base85Variant::Rfc1924 });
+        codecs.register(Base85Codec { variant: Base85Variant::Z85 });
+        codecs.register(PunycodeCodec);
+        codecs.register(QuotedPrintableCodec { binary: false });
+        codecs.register(MimeWordCodec);
+        codecs.register(HeaderParamCodec { name: "filename".to_string(), language: String::new() });
+        codecs.register(HtmlCodec { context: HtmlContext::Text });
+        codecs.register(HtmlCodec { context: HtmlContext::Attribute });
+        codecs.register(Base32Codec { alphabet: Base32Alphabet::Standard, pad: true });
+        codecs.register(Base32Codec { alphabet: Base32Alphabet::Hex, pad: true });
+        codecs.register(Base32Codec { alphabet: Base32Alphabet::Crockford, pad: false });
+        codecs
+    }
+}
+
+//ANCHOR - Codec chain
+///Codecs run one after another, decoding runs them in reverse
+pub struct CodecChain {
+    codecs: Vec<Arc<dyn Codec>>,
+    name: String,
+}
+
+impl CodecChain {
+    pub fn new(codecs: Vec<Arc<dyn Codec>>) -> Self {
+        let name = codecs.iter().map(|c| c.name()).collect::<Vec<_>>().join(",");
+        CodecChain { codecs, name }
+    }
+
+    ///The result of every stage, the last one is the encoded value
+    pub fn encode_stages(&self, inp: &[u8]) -> Vec<String> {
+        let mut stages: Vec<String> = Vec::new();
+        for codec in &self.codecs {
+            let stage = match stages.last() {
+                Some(prev) => codec.encode(prev.as_bytes()),
+                None => codec.encode(inp),
+            };
+            stages.push(stage);
+        }
+        stages
+    }
+
+    ///The result of every stage but the last, and the decoded bytes or the error that stopped the chain
+    pub fn decode_stages(&self, inp: &str) -> (Vec<String>, Result<Vec<u8>, UrlencodeError>) {
+        let mut stages = Vec::new();
+        let mut value = inp.to_string();
+        for (idx, codec) in self.codecs.iter().rev().enumerate() {
+            let bytes = match codec.decode(&value) {
+                Ok(bytes) => bytes,
+                Err(error) => return (stages, Err(error)),
+            };
+            if idx + 1 == self.codecs.len() {
+                return (stages, Ok(bytes));
+            }
+            //the next codec decodes text, so the stages in between must be UTF-8
+            value = match bytes_to_string(bytes) {
+                Ok(value) => value,
+                Err(error) => return (stages, Err(error)),
+            };
+            stages.push(value.clone());
+        }
+        (stages, Ok(value.into_bytes()))
+    }
+}
+
+impl Codec for CodecChain {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn encode(&self, inp: &[u8]) -> String {
+        match self.encode_stages(inp).pop() {
+            Some(result) => result,
+            None => String::from_utf8_lossy(inp).into_owned(),
+        }
+    }
+    fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+        self.decode_stages(inp).1
+    }
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Detect
+
+///One possible reading of the input, the score goes from 0 to 100
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guess {
+    pub name: &'static str,
+    pub score: u32,
+    pub decoded: String,
+}
+
+///One layer peeled off by decode_recursive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pub name: &'static str,
+    pub decoded: String,
+}
+
+//a guess below this is not trusted enough to decode recursively
+const DETECT_THRESHOLD: u32 = 60;
+//stop even when it keeps changing, a loop of codecs could go on forever
+const DETECT_MAX_LAYERS: usize = 16;
+
+//ANCHOR - Detect encoding
+///Score the input as every encoding it could be, the best guess first.
+///Only readings that decode to printable text are kept.
+pub fn detect(inp: &str) -> Vec<Guess> {
+    let inp = inp.trim();
+    let mut guesses = Vec::new();
+    if inp.is_empty() {
+        return guesses;
+    }
+    let len = inp.len() as u32;
+
+    let escapes = inp.as_bytes().windows(3).filter(|w| w[0] == b'%' && w[1].is_ascii_hexdigit() && w[2].is_ascii_hexdigit()).count() as u32;
+    if escapes > 0 {
+        if let Ok(decoded) = decoding_percent(inp) {
+            let score = 60 + 40 * escapes * 3 / len;
+            if inp.contains('+') {
+                if let Ok(form) = decoding_form(inp) {
+                    push_guess(&mut guesses, "form", score - 5, form);
+                }
+            }
+            push_guess(&mut guesses, "percent", score, decoded);
+        }
+    } else if inp.contains('+') && !inp.contains(' ') {
+        if let Ok(form) = decoding_form(inp) {
+            push_guess(&mut guesses, "form", 30, form);
+        }
+    }
+
+    let body = inp.trim_end_matches('=');
+    let padding = inp.len() - body.len();
+    let is_url = body.contains(['-', '_']);
+    if body.len() >= 4
+        && padding <= 2
+        && (padding == 0 || inp.len().is_multiple_of(4))
+        && body.len() % 4 != 1
+        && body.bytes().all(|b| b.is_ascii_alphanumeric() || if is_url { b == b'-' || b == b'_' } else { b == b'+' || b == b'/' })
+    {
+        if let Ok(decoded) = frombase64(inp) {
+            let mut score = 40;
+            if inp.len().is_multiple_of(4) {
+                score += 20;
+            }
+            if body.bytes().any(|b| !b.is_ascii_alphabetic()) || padding > 0 {
+                score += 15; //a plain word is rarely meant as Base64
+            }
+            push_guess(&mut guesses, if is_url { "base64url" } else { "base64" }, score, decoded);
+        }
+    }
+
+    if inp.len() >= 2 && inp.len().is_multiple_of(2) && inp.bytes().all(|b| b.is_ascii_hexdigit()) {
+        if let Some(decoded) = fromhex(inp).ok().and_then(|bytes| String::from_utf8(bytes).ok()) {
+            let mut score = 40;
+            if inp.len() >= 8 {
+                score += 20;
+            }
+            if inp.bytes().any(|b| b.is_ascii_digit()) && inp.bytes().any(|b| b.is_ascii_alphabetic()) {
+                score += 10;
+            }
+            push_guess(&mut guesses, "hex", score, decoded);
+        }
+    }
+
+    let decoded = decoding_html(inp);
+    if decoded != inp {
+        let removed = inp.len().saturating_sub(decoded.len()) as u32;
+        push_guess(&mut guesses, "html", (60 + 40 * removed / len).min(100), decoded);
+    }
+
+    guesses.sort_by_key(|guess| std::cmp::Reverse(guess.score));
+    guesses
+}
+
+//keep a guess only when it reads as text
+fn push_guess(guesses: &mut Vec<Guess>, name: &'static str, score: u32, decoded: String) {
+    let total = decoded.chars().count();
+    let printable = decoded.chars().filter(|c| !c.is_control() || c.is_whitespace()).count();
+    if total > 0 && printable * 20 >= total * 19 {
+        guesses.push(Guess { name, score: score.min(100), decoded });
+    }
+}
+
+//ANCHOR - Decode recursively
+///Apply the best guess until the result stops changing, one layer per step
+pub fn decode_recursive(inp: &str) -> Vec<Layer> {
+    let mut layers: Vec<Layer> = Vec::new();
+    let mut value = inp.to_string();
+    while layers.len() < DETECT_MAX_LAYERS {
+        let best = detect(&value).into_iter().find(|guess| guess.score >= DETECT_THRESHOLD && guess.decoded != value);
+        match best {
+            Some(guess) => {
+                value = guess.decoded.clone();
+                layers.push(Layer { name: guess.name, decoded: guess.decoded });
+            }
+            None => break,
+        }
+    }
+    layers
+}
+
+//------------------------------------------------------------------------------------------------------------------------------------------------------------------------------
+//Unit test
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(inp: &[u8]) -> String {
+        tohex(inp, false, HexSeparator::None)
+    }
+
+    struct RejectCodec;
+
+    impl Codec for RejectCodec {
+        fn name(&self) -> &str {
+            "reject"
+        }
+        fn encode(&self, inp: &[u8]) -> String {
+            String::from_utf8_lossy(inp).into_owned()
+        }
+        fn decode(&self, inp: &str) -> Result<Vec<u8>, UrlencodeError> {
+            Err(UrlencodeError::Codec(format!("cannot decode '{}'", inp).into()))
+        }
+    }
+
+    #[test]
+    fn testcustomcodec() {
+        let mut codecs = CodecRegistry::default();
+        codecs.register(RejectCodec);
+        let error = codecs.get("Reject").unwrap().decode("x").unwrap_err();
+        assert_eq!(error.to_string(), "cannot decode 'x'");
+        assert_eq!(error.exit_code(), 65);
+        assert!(matches!(codecs.get("nope"), Err(UrlencodeError::UnknownCodec { .. })));
+    }
+
+    #[test]
+    fn testcodecchain() {
+        let codecs = CodecRegistry::default();
+        let chain = codecs.chain("base64, percent").unwrap();
+        assert_eq!(chain.name(), "base64,percent");
+        assert_eq!(chain.encode_stages(b"hi?"), ["aGk/", "aGk%2F"]);
+        assert_eq!(chain.encode(b"hi?"), "aGk%2F");
+
+        //decoding runs the codecs in reverse and keeps the stages in between
+        let (stages, result) = chain.decode_stages("aGk%2F");
+        assert_eq!(stages, ["aGk/"]);
+        assert_eq!(result.unwrap(), b"hi?");
+        assert_eq!(codecs.chain("hex|base64").unwrap().decode("NjE2Mg==").unwrap(), b"ab");
+
+        let (stages, result) = chain.decode_stages("%ZZ");
+        assert!(stages.is_empty() && matches!(result, Err(UrlencodeError::InvalidCharacter { .. })));
+        let (stages, result) = codecs.chain("base64,hex").unwrap().decode_stages("ff");
+        assert!(stages.is_empty() && matches!(result, Err(UrlencodeError::InvalidUtf8 { offset: 0 })));
+        assert!(matches!(codecs.chain("base64,nope"), Err(UrlencodeError::UnknownCodec { .. })));
+    }
+
+    #[test]
+    fn testdetect() {
+        let best = |inp: &str| detect(inp).into_iter().next().map(|guess| (guess.name, guess.decoded));
+        assert_eq!(best("aGVsbG8gd29ybGQ="), Some(("base64", "hello world".to_string())));
+        assert_eq!(best("48656c6c6f21"), Some(("hex", "Hello!".to_string())));
+        assert_eq!(best("&lt;b&gt;"), Some(("html", "<b>".to_string())));
+        assert_eq!(best("%252520"), Some(("percent", "%2520".to_string())));
+        assert_eq!(best("hello"), None);
+        assert_eq!(best("  "), None);
+        assert!(detect("a+b")[0].score < DETECT_THRESHOLD); //a lone '+' is too weak to act on
+    }
+
+    #[test]
+    fn testdecoderecursive() {
+        let layer = |name, decoded: &str| Layer { name, decoded: decoded.to_string() };
+        let percent = ["%2520", "%20", " "].map(|decoded| layer("percent", decoded));
+        assert_eq!(decode_recursive("%252520"), percent);
+        assert_eq!(decode_recursive("JTNDYiUzRQ=="), [layer("base64", "%3Cb%3E"), layer("percent", "<b>")]);
+        assert_eq!(decode_recursive("hello"), []);
+        assert_eq!(decode_recursive("a+b"), []);
+    }
+
+    #[test]
+    fn testsha256() {
+        //FIPS 180-2 appendix B.1 and the digest of the empty message
+        assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn testbase58check() {
+        //the address of the genesis block: version 0x00 and a 20-byte hash
+        let data = fromhex("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        let address = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+        assert_eq!(tobase58check(&data, Base58Alphabet::Bitcoin), address);
+        assert_eq!(frombase58check(address, Base58Alphabet::Bitcoin).unwrap(), data);
+
+        let codec = Base58CheckCodec { alphabet: Base58Alphabet::Bitcoin, version: Some(0) };
+        assert_eq!(codec.decode(address).unwrap(), data[1..]);
+    }
+
+    #[test]
+    fn testbase58checkerrors() {
+        //last character changed
+        assert!(matches!(
+            frombase58check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb", Base58Alphabet::Bitcoin),
+            Err(UrlencodeError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(frombase58check("1111", Base58Alphabet::Bitcoin), Err(UrlencodeError::MissingChecksum(4))));
+
+        let codec = Base58CheckCodec { alphabet: Base58Alphabet::Bitcoin, version: Some(5) };
+        assert!(matches!(
+            codec.decode("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"),
+            Err(UrlencodeError::VersionMismatch { expected: 5, found: 0 })
+        ));
+    }
+
+    //RFC 3492 section 7.1
+    const PUNYCODE_SAMPLES: [(&str, &str); 13] = [
+        ("ليهمابتكلموشعربي؟", "egbpdaj6bu4bxfgehfvwxn"),
+        ("他们为什么不说中文", "ihqwcrb4cv8a8dqg056pqjye"),
+        ("他們爲什麽不說中文", "ihqwctvzc91f659drss3x8bo0yb"),
+        ("Pročprostěnemluvíčesky", "Proprostnemluvesky-uyb24dma41a"),
+        ("למההםפשוטלאמדבריםעברית", "4dbcagdahymbxekheh6e0a7fei0b"),
+        ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+        ("安室奈美恵-with-SUPER-MONKEYS", "-with-SUPER-MONKEYS-pc58ag80a8qai00g7n9n"),
+        ("Hello-Another-Way-それぞれの場所", "Hello-Another-Way--fc4qua05auwb3674vfr0b"),
+        ("ひとつ屋根の下2", "2-u9tlzr9756bt3uc0v"),
+        ("MajiでKoiする5秒前", "MajiKoi5-783gue6qz075azm5e"),
+        ("パフィーdeルンバ", "de-jg4avhby1noc0d"),
+        ("そのスピードで", "d9juau41awczczp"),
+        ("-> $1.00 <-", "-> $1.00 <--"),
+    ];
+
+    #[test]
+    fn testpunycode() {
+        for (unicode, puny) in PUNYCODE_SAMPLES {
+            assert_eq!(topunycode(unicode), puny);
+            assert_eq!(frompunycode(puny).unwrap(), unicode);
+        }
+        //the digits are case-insensitive, the sample has a mixed-case annotation
+        assert_eq!(frompunycode("b1abfaaepdrnnbgefbaDotcwatmq2g4l").unwrap(), "почемужеонинеговорятпорусски");
+    }
+
+    #[test]
+    fn testpunycodeoverflow() {
+        assert!(matches!(frompunycode("99999999999999"), Err(UrlencodeError::InvalidPunycode { .. })));
+        assert!(matches!(frompunycode("a-zzzzzzzzzzzzzzzz"), Err(UrlencodeError::InvalidPunycode { .. })));
+    }
+
+    #[test]
+    fn testdomaintoascii() {
+        assert_eq!(domain_to_ascii("Bücher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(domain_to_ascii("münchen\u{3002}de").unwrap(), "xn--mnchen-3ya.de");
+        assert_eq!(domain_to_ascii("example.com").unwrap(), "example.com");
+        assert!(domain_to_ascii("xn--99999999999999.com").is_err());
+        assert_eq!(domain_to_unicode("xn--bcher-kva.example"), "bücher.example");
+    }
+
+    //RFC 3986 section 5.4, the base of every example is "http://a/b/c/d;p?q"
+    const RESOLVE_NORMAL: [(&str, &str); 23] = [
+        ("g:h", "g:h"),
+        ("g", "http://a/b/c/g"),
+        ("./g", "http://a/b/c/g"),
+        ("g/", "http://a/b/c/g/"),
+        ("/g", "http://a/g"),
+        ("//g", "http://g"),
+        ("?y", "http://a/b/c/d;p?y"),
+        ("g?y", "http://a/b/c/g?y"),
+        ("#s", "http://a/b/c/d;p?q#s"),
+        ("g#s", "http://a/b/c/g#s"),
+        ("g?y#s", "http://a/b/c/g?y#s"),
+        (";x", "http://a/b/c/;x"),
+        ("g;x", "http://a/b/c/g;x"),
+        ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+        ("", "http://a/b/c/d;p?q"),
+        (".", "http://a/b/c/"),
+        ("./", "http://a/b/c/"),
+        ("..", "http://a/b/"),
+        ("../", "http://a/b/"),
+        ("../g", "http://a/b/g"),
+        ("../..", "http://a/"),
+        ("../../", "http://a/"),
+        ("../../g", "http://a/g"),
+    ];
+
+    const RESOLVE_ABNORMAL: [(&str, &str); 19] = [
+        ("../../../g", "http://a/g"),
+        ("../../../../g", "http://a/g"),
+        ("/./g", "http://a/g"),
+        ("/../g", "http://a/g"),
+        ("g.", "http://a/b/c/g."),
+        (".g", "http://a/b/c/.g"),
+        ("g..", "http://a/b/c/g.."),
+        ("..g", "http://a/b/c/..g"),
+        ("./../g", "http://a/b/g"),
+        ("./g/.", "http://a/b/c/g/"),
+        ("g/./h", "http://a/b/c/g/h"),
+        ("g/../h", "http://a/b/c/h"),
+        ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+        ("g;x=1/../y", "http://a/b/c/y"),
+        ("g?y/./x", "http://a/b/c/g?y/./x"),
+        ("g?y/../x", "http://a/b/c/g?y/../x"),
+        ("g#s/./x", "http://a/b/c/g#s/./x"),
+        ("g#s/../x", "http://a/b/c/g#s/../x"),
+        ("http:g", "http:g"),
+    ];
+
+    #[test]
+    fn testurlparse() {
+        let url = Url::parse("https://user:pw@Example.com:8080/a/b/?q=1&r#frag").unwrap();
+        assert_eq!(url.scheme.as_deref(), Some("https"));
+        assert_eq!(url.userinfo.as_deref(), Some("user:pw"));
+        assert_eq!(url.host.as_deref(), Some("Example.com"));
+        assert_eq!(url.port, Some(8080));
+        assert_eq!(url.path, ["", "a", "b", ""]);
+        assert_eq!(url.query.as_deref(), Some("q=1&r"));
+        assert_eq!(url.fragment.as_deref(), Some("frag"));
+
+        let ipv6 = Url::parse("http://[::1]:80/").unwrap();
+        assert_eq!((ipv6.host.as_deref(), ipv6.port), (Some("[::1]"), Some(80)));
+        assert_eq!(Url::parse("file:///etc/hosts").unwrap().host.as_deref(), Some(""));
+        assert!(Url::parse("mailto:a@x.de").unwrap().host.is_none());
+        assert_eq!(Url::parse("http://h:99999/"), Err(UrlError::InvalidPort("99999".to_string())));
+    }
+
+    #[test]
+    fn testurlroundtrip() {
+        for inp in [
+            "https://user:pw@example.com:8080/a/b/?q=1&r#frag",
+            "http://[::1]:80/p%20q?a=1",
+            "file:///etc/hosts",
+            "mailto:a@x.de",
+            "urn:isbn:0451450523",
+            "//example.com/path",
+            "/relative/path?x#y",
+            "?only=query",
+            "",
+        ] {
+            assert_eq!(Url::parse(inp).unwrap().to_string(), inp);
+        }
+    }
+
+    #[test]
+    fn testurlencoded() {
+        let encode = |inp: &str| encoding_percent_url(inp).unwrap();
+        assert_eq!(
+            encode("https://user name@exämple.com:8080/a b/ü?q=a b&x=ü#frag ment"),
+            "https://user%20name@xn--exmple-cua.com:8080/a%20b/%C3%BC?q=a%20b&x=%C3%BC#frag%20ment"
+        );
+        //valid escapes are kept, a bare '%' is escaped
+        assert_eq!(encode("http://h/p%20q?a=%zz"), "http://h/p%20q?a=%25zz");
+        assert_eq!(encode("http://h/a?b?c#d#e"), "http://h/a?b?c#d%23e");
+        assert_eq!(encode("mailto:a b@x.de"), "mailto:a%20b@x.de");
+    }
+
+    //the printable ASCII characters a set encodes
+    fn printable(set: EncodeSet) -> String {
+        (0x20..0x7F).filter(|&b| set.contains(b)).map(char::from).collect()
+    }
+
+    #[test]
+    fn testencodesets() {
+        //WHATWG URL standard section 1.3, each set builds on the one before
+        assert_eq!(printable(EncodeSet::FRAGMENT), " \"<>`");
+        assert_eq!(printable(EncodeSet::QUERY), " \"#<>");
+        assert_eq!(printable(EncodeSet::SPECIAL_QUERY), " \"#'<>");
+        assert_eq!(printable(EncodeSet::PATH), " \"#<>?^`{}");
+        assert_eq!(printable(EncodeSet::USERINFO), " \"#/:;<=>?@[\\]^`{|}");
+        assert_eq!(printable(EncodeSet::COMPONENT), " \"#$%&+,/:;<=>?@[\\]^`{|}");
+        assert_eq!(printable(EncodeSet::FORM), " !\"#$%&'()+,/:;<=>?@[\\]^`{|}~");
+        //RFC 3986 unreserved, the legacy set of the first version and RFC 8187 attr-char
+        assert_eq!(printable(EncodeSet::UNRESERVED), " !\"#$%&'()*+,/:;<=>?@[\\]^`{|}");
+        assert_eq!(printable(EncodeSet::LEGACY), " \"#%&',/:;<=>?@[\\]^`{|}~");
+        assert_eq!(printable(EncodeSet::ATTR_CHAR), " \"%'()*,/:;<=>?@[\\]{}");
+
+        //C0 controls, DEL and non-ASCII are in every set but NONE
+        for set in [EncodeSet::CONTROLS, EncodeSet::FRAGMENT, EncodeSet::QUERY, EncodeSet::FORM, EncodeSet::LEGACY] {
+            assert!((0x00..0x20).chain([0x7F, 0x80, 0xFF]).all(|b| set.contains(b)));
+        }
+        assert_eq!(printable(EncodeSet::NONE), "");
+        assert!(EncodeSet::NONE.contains(0x80));
+        assert!(EncodeSet::NONE.remove(0xC3).contains(0xC3)); //non-ASCII can not be removed
+    }
+
+    #[test]
+    fn testencodesetnames() {
+        assert_eq!("Path".parse::<EncodeSet>(), Ok(EncodeSet::PATH));
+        assert_eq!("rfc3986".parse::<EncodeSet>(), Ok(EncodeSet::UNRESERVED));
+        assert!("nope".parse::<EncodeSet>().is_err());
+        assert_eq!(encoding_percent_set("a b/ü?", &EncodeSet::PATH), "a%20b/%C3%BC%3F");
+        assert_eq!(encoding_percent_set("a b/~", &EncodeSet::UNRESERVED.remove(b'/')), "a%20b/~");
+    }
+
+    #[test]
+    fn testform() {
+        //a space is '+', so a literal '+' has to be %2B
+        assert_eq!(encoding_form("a+b c&d=é*~"), "a%2Bb+c%26d%3D%C3%A9*%7E");
+        assert_eq!(decoding_form("a%2Bb+c%26d%3D%C3%A9*%7E").unwrap(), "a+b c&d=é*~");
+        assert_eq!(decoding_form("1+1%3D2").unwrap(), "1 1=2");
+        for inp in ["+", " + ", "%2B", "a+b=c&d", "100% sure"] {
+            assert_eq!(decoding_form(&encoding_form(inp)).unwrap(), inp);
+        }
+        assert_eq!(encoding_form_pairs(&[("q", "a+b c"), ("lang", "en")]), "q=a%2Bb+c&lang=en");
+    }
+
+    #[test]
+    fn testpercenterrors() {
+        assert_eq!(decoding_percent("a%20b%C3%A9").unwrap(), "a bé");
+        assert!(matches!(decoding_percent("%"), Err(UrlencodeError::TruncatedEscape { offset: 0 })));
+        assert!(matches!(decoding_percent("a%4"), Err(UrlencodeError::TruncatedEscape { offset: 1 })));
+        assert!(matches!(decoding_percent("%G1"), Err(UrlencodeError::InvalidCharacter { offset: 1, byte: b'G' })));
+        assert!(matches!(decoding_percent("%4G"), Err(UrlencodeError::InvalidCharacter { offset: 2, byte: b'G' })));
+        //the UTF-8 offset counts decoded bytes, the raw bytes are still there for binary mode
+        assert!(matches!(decoding_percent("%41%42%43%FF"), Err(UrlencodeError::InvalidUtf8 { offset: 3 })));
+        assert_eq!(decoding_percent_bytes("%41%42%43%FF").unwrap(), [0x41, 0x42, 0x43, 0xFF]);
+    }
+
+    #[test]
+    fn testexitcodes() {
+        use std::io::{Error, ErrorKind};
+        assert_eq!(UrlencodeError::Io(Error::from(ErrorKind::NotFound)).exit_code(), 66);
+        assert_eq!(UrlencodeError::Io(Error::from(ErrorKind::AlreadyExists)).exit_code(), 73);
+        assert_eq!(UrlencodeError::Io(Error::from(ErrorKind::PermissionDenied)).exit_code(), 74);
+        assert_eq!(UrlencodeError::Empty.exit_code(), 66);
+        assert_eq!(UrlencodeError::UnknownCodec { name: String::new(), known: String::new() }.exit_code(), 64);
+        assert_eq!(UrlencodeError::BadPadding { offset: 0 }.exit_code(), 65);
+        assert_eq!(UrlencodeError::Url(UrlError::InvalidPort("x".to_string())).exit_code(), 65);
+
+        //offsets inside a slice move, a decoded-byte offset does not
+        assert!(matches!(UrlencodeError::TruncatedEscape { offset: 1 }.offset_by(4), UrlencodeError::TruncatedEscape { offset: 5 }));
+        assert!(matches!(UrlencodeError::InvalidUtf8 { offset: 1 }.offset_by(4), UrlencodeError::InvalidUtf8 { offset: 1 }));
+    }
+
+    fn pair(k: &str, v: Option<&str>) -> (String, Option<String>) {
+        (k.to_string(), v.map(str::to_string))
+    }
+
+    #[test]
+    fn testparsequery() {
+        let (pairs, range) = parse_query("http://h/p?a=1&b=%20x&a=2#f").unwrap();
+        assert_eq!(pairs, [pair("a", Some("1")), pair("b", Some(" x")), pair("a", Some("2"))]);
+        assert_eq!(range, 11..25);
+
+        //a relative reference, a bare query and one whose first key looks like a scheme
+        assert_eq!(parse_query("/search?q=1&r=2").unwrap().0, [pair("q", Some("1")), pair("r", Some("2"))]);
+        assert_eq!(parse_query("?a=1").unwrap(), (vec![pair("a", Some("1"))], 1..4));
+        assert_eq!(parse_query("a:b=1&c=2").unwrap().0, [pair("a:b", Some("1")), pair("c", Some("2"))]);
+        assert_eq!(parse_query("redirect=http://x/").unwrap().0, [pair("redirect", Some("http://x/"))]);
+        assert_eq!(parse_query("http://h/p").unwrap(), (Vec::new(), 10..10));
+
+        //"c" has no value, "c=" has an empty one
+        assert_eq!(parse_query("c&c=&d=+&&").unwrap().0, [pair("c", None), pair("c", Some("")), pair("d", Some(" "))]);
+    }
+
+    #[test]
+    fn testparsequeryerrors() {
+        assert!(matches!(parse_query("a=%ZZ"), Err(UrlencodeError::InvalidCharacter { offset: 3, byte: b'Z' })));
+        assert!(matches!(parse_query("/p?a=1&b%2=2"), Err(UrlencodeError::TruncatedEscape { offset: 8 })));
+        assert!(matches!(parse_query("http://h/?a=1&b=%4"), Err(UrlencodeError::TruncatedEscape { offset: 16 })));
+    }
+
+    #[test]
+    fn testinputsource() {
+        assert!(matches!("-".parse::<StringOrPath>().unwrap(), StringOrPath::Stdin));
+        assert!(matches!("Cargo.toml".parse::<StringOrPath>().unwrap(), StringOrPath::Path(_)));
+        assert!(matches!("a b".parse::<StringOrPath>().unwrap(), StringOrPath::String(_)));
+        //--text and --file win over the guess
+        assert!(matches!(StringOrPath::Path("Cargo.toml".into()).forced(true, false), StringOrPath::String(s) if s == "Cargo.toml"));
+        assert!(matches!(StringOrPath::Stdin.forced(true, false), StringOrPath::String(s) if s == "-"));
+        assert!(matches!(StringOrPath::String("x".to_string()).forced(false, true), StringOrPath::Path(_)));
+    }
+
+    #[test]
+    fn teststreamlines() {
+        //every line is handled as it comes, a failing one does not stop the rest
+        let reader = io::Cursor::new("a%20b\n%ZZ\n\nc%21\n");
+        let errors = streamlines(reader, FileLocate::Plain, decoding_percent).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].row), (2, 1));
+    }
+
+    #[test]
+    fn testqueryrows() {
+        let (rows, error) = queryrows("?c&c=&a=1");
+        assert!(error.is_none());
+        let expected = [("c", NO_VALUE), ("c", ""), ("a", "1")];
+        assert_eq!(rows, expected.map(|(k, v)| (k.to_string(), v.to_string())));
+        assert_eq!(queryrows("http://h/p").0, []);
+
+        let (rows, error) = queryrows("a=%ZZ");
+        assert_eq!(rows, [("a=%ZZ".to_string(), String::new())]);
+        assert!(matches!(error, Some(UrlencodeError::InvalidCharacter { offset: 3, .. })));
+    }
+
+    #[test]
+    fn testeditquery() {
+        let set = [QueryEdit::Set("a".to_string(), "9".to_string())];
+        assert_eq!(edit_query("HTTP://h/p%7e?q=1&a=2#frag", &set).unwrap(), "HTTP://h/p%7e?q=1&a=9#frag");
+        assert_eq!(edit_query("/search?q=1&a=2", &set).unwrap(), "/search?q=1&a=9");
+        assert_eq!(edit_query("?a=1", &set).unwrap(), "?a=9");
+        assert_eq!(edit_query("a:b=1", &set).unwrap(), "a%3Ab=1&a=9");
+        assert_eq!(edit_query("utm:src=x&a=1", &set).unwrap(), "utm%3Asrc=x&a=9");
+        assert_eq!(edit_query("http://h/p#f", &set).unwrap(), "http://h/p?a=9#f");
+
+        let edits = [QueryEdit::Remove("a".to_string()), QueryEdit::Add("c".to_string(), "x y".to_string()), QueryEdit::Sort];
+        assert_eq!(edit_query("http://h/?d&a=1&b=", &edits).unwrap(), "http://h/?b=&c=x+y&d");
+        assert_eq!(edit_query("http://h/?a=1#f", &[QueryEdit::Remove("a".to_string())]).unwrap(), "http://h/#f");
+    }
+
+    fn written(writer: &mut dyn RowWriter, rows: &[Row]) -> String {
+        let mut out = Vec::new();
+        writer.begin(&mut out).unwrap();
+        for row in rows {
+            writer.row(&mut out, row).unwrap();
+        }
+        writer.end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn testcsvtsv() {
+        let rows = [
+            Row { input: "a,b".to_string(), result: "say \"hi\"".to_string(), error: None },
+            Row { input: "x\ty".to_string(), result: "1\r\n2".to_string(), error: Some("bad \\ input".to_string()) },
+        ];
+        assert_eq!(
+            written(&mut CsvWriter, &rows),
+            "input,result,error\n\"a,b\",\"say \"\"hi\"\"\",\nx\ty,\"1\r\n2\",bad \\ input\n"
+        );
+        assert_eq!(
+            written(&mut TsvWriter, &rows),
+            "input\tresult\terror\na,b\tsay \"hi\"\t\nx\\ty\t1\\r\\n2\tbad \\\\ input\n"
+        );
+        assert_eq!(csvfield("plain"), "plain");
+        assert_eq!(csvfield("\""), "\"\"\"\"");
+        assert_eq!(tsvfield("\\t"), "\\\\t"); //a literal backslash-t stays apart from an escaped tab
+    }
+
+    #[test]
+    fn testjsonmarkdown() {
+        let rows = [Row { input: "a\"b\\".to_string(), result: "l1\nl2|\u{1}".to_string(), error: Some("e".to_string()) }];
+        assert_eq!(
+            written(&mut JsonWriter::default(), &rows),
+            "[\n  {\"input\":\"a\\\"b\\\\\",\"result\":\"l1\\nl2|\\u0001\",\"error\":\"e\"}\n]\n"
+        );
+        assert_eq!(written(&mut JsonWriter::default(), &[]), "[]\n");
+        assert_eq!(
+            written(&mut MarkdownWriter, &rows),
+            "| Input | Result | Error |\n| --- | --- | --- |\n| a\"b\\\\ | l1<br>l2\\|\u{1} | e |\n"
+        );
+    }
+
+    #[test]
+    fn testhtmlreport() {
+        let rows = [
+            Row { input: "%3Cscript%3E".to_string(), result: "<script>".to_string(), error: None },
+            Row { input: "%ZZ\"".to_string(), result: String::new(), error: Some("bad <b>".to_string()) },
+        ];
+        let report = written(&mut HtmlWriter::default(), &rows);
+        assert!(report.starts_with("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">"));
+        assert!(report.ends_with("</body>\n</html>\n"));
+        assert!(!report.contains("<b>"));
+        assert!(report.contains("<mark>&lt;</mark>script<mark>&gt;</mark>"));
+        assert!(report.contains("<tr class=\"error\">") && report.contains("%ZZ\"</span>") && report.contains("bad &lt;b&gt;"));
+        assert_eq!(report.matches("<script>").count(), 1); //only the report's own script
+    }
+
+    #[test]
+    fn testcreatefile() {
+        let path = std::env::temp_dir().join(format!("urlencode-clobber-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        createfile(&path, false).unwrap();
+        let error = createfile(&path, false).unwrap_err();
+        assert_eq!(error.exit_code(), 73);
+        assert!(createfile(&path, true).is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn testdiffmarks() {
+        let (removed, added) = diffmarks("a b/c", "a%20b%2Fc");
+        assert_eq!(htmlmarked("a b/c", &removed), "a<mark> </mark>b<mark>/</mark>c");
+        assert_eq!(htmlmarked("a%20b%2Fc", &added), "a<mark>%20</mark>b<mark>%2F</mark>c");
+
+        //flags are per char, not per byte
+        let (removed, added) = diffmarks("né<", "n%C3%A9%3C");
+        assert_eq!(removed, [false, true, true]);
+        assert_eq!(htmlmarked("né<", &removed), "n<mark>é&lt;</mark>");
+        assert_eq!(added.iter().filter(|&&f| f).count(), 9);
+
+        assert_eq!(diffmarks("same", "same"), (vec![false; 4], vec![false; 4]));
+        assert_eq!(diffmarks("", "ab"), (vec![], vec![true, true]));
+        assert_eq!(diffmarks("ab", "xy"), (vec![true, true], vec![true, true]));
+    }
+
+    #[test]
+    fn testremovedotsegments() {
+        assert_eq!(remove_dot_segments("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(remove_dot_segments("mid/content=5/../6"), "mid/6");
+        //a first segment that starts with a multi-byte character
+        assert_eq!(remove_dot_segments("é/x/../y"), "é/y");
+        assert_eq!(remove_dot_segments("ü@x.de"), "ü@x.de");
+        assert_eq!(normalize_url("mailto:ü@x.de", &NormalizeOptions::default()).unwrap(), "mailto:ü@x.de");
+    }
+
+    #[test]
+    fn testresolve() {
+        for (reference, target) in RESOLVE_NORMAL.iter().chain(RESOLVE_ABNORMAL.iter()) {
+            assert_eq!(resolve("http://a/b/c/d;p?q", reference).unwrap(), *target, "reference {:?}", reference);
+        }
+    }
+
+    //RFC 4648 section 10
+    const BASE32_VECTORS: [(&str, &str, &str); 7] = [
+        ("", "", ""),
+        ("f", "MY======", "CO======"),
+        ("fo", "MZXQ====", "CPNG===="),
+        ("foo", "MZXW6===", "CPNMU==="),
+        ("foob", "MZXW6YQ=", "CPNMUOG="),
+        ("fooba", "MZXW6YTB", "CPNMUOJ1"),
+        ("foobar", "MZXW6YTBOI======", "CPNMUOJ1E8======"),
+    ];
+
+    //RFC 4648 section 10
+    const BASE64_VECTORS: [(&str, &str); 7] =
+        [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+
+    #[test]
+    fn testbase64() {
+        for (data, encoded) in BASE64_VECTORS {
+            assert_eq!(tobase64_with(data.as_bytes(), Base64Alphabet::Standard, true), encoded);
+            assert_eq!(tobase64_with(data.as_bytes(), Base64Alphabet::Standard, false), encoded.trim_end_matches('='));
+            assert_eq!(frombase64_bytes(encoded).unwrap(), data.as_bytes());
+            assert_eq!(frombase64_bytes(encoded.trim_end_matches('=')).unwrap(), data.as_bytes());
+        }
+        //base64url swaps '+' '/' for '-' '_', the decoder takes both alphabets and skips line breaks
+        let data = [0xFB, 0xFF, 0xBF];
+        assert_eq!(tobase64_with(&data, Base64Alphabet::Standard, true), "+/+/");
+        assert_eq!(tobase64_with(&data, Base64Alphabet::UrlSafe, true), "-_-_");
+        assert_eq!(frombase64_bytes("-_-_").unwrap(), data);
+        assert_eq!(frombase64_bytes("Zm9v\r\nYmFy\n").unwrap(), b"foobar");
+    }
+
+    #[test]
+    fn testbase64errors() {
+        assert!(matches!(frombase64_bytes("Zm9v!"), Err(UrlencodeError::InvalidCharacter { offset: 4, byte: b'!' })));
+        assert!(matches!(frombase64_bytes("Zg=a"), Err(UrlencodeError::BadPadding { offset: 2 })));
+        assert!(matches!(frombase64_bytes("Z"), Err(UrlencodeError::BadPadding { offset: 1 })));
+        assert!(matches!(frombase64_bytes("Zg==="), Err(UrlencodeError::BadPadding { offset: 2 })));
+        assert!(matches!(frombase64_bytes("Zm9vYg==Zg"), Err(UrlencodeError::BadPadding { offset: 6 })));
+    }
+
+    #[test]
+    fn testbinaryroundtrip() {
+        //every byte value, runs of zeros and line breaks survive an encoding that is meant for binary data
+        let data: Vec<u8> = (0..=255).chain([0, 0, 0, 0, b'\r', b'\n', b'\n']).collect();
+        let codecs = CodecRegistry::default();
+        for name in ["percent", "form", "base64", "base64url", "base32", "base32hex", "crockford", "hex", "base58", "base58check", "ascii85", "adobe85", "rfc1924", "z85"] {
+            let codec = codecs.get(name).unwrap();
+            assert_eq!(codec.decode(&codec.encode(&data)).unwrap(), data, "{}", name);
+
+            //a wrapped binary payload keeps its line breaks, the alphabet decoders skip them
+            if name != "percent" && name != "form" {
+                let encoded = codec.encode(&data);
+                let mid = encoded.char_indices().nth(encoded.chars().count() / 2).map_or(0, |(idx, _)| idx);
+                let wrapped = format!("{}\n{}\r\n", &encoded[..mid], &encoded[mid..]);
+                assert_eq!(codec.decode(&wrapped).unwrap(), data, "{} wrapped", name);
+            }
+        }
+        let qp = QuotedPrintableCodec { binary: true };
+        assert_eq!(qp.decode(&qp.encode(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn testheaderparams() {
+        let header = encoding_header_param("filename", "naïve \"a\".pdf", "");
+        assert_eq!(header, "filename=\"naive \\\"a\\\".pdf\"; filename*=UTF-8''na%C3%AFve%20%22a%22.pdf");
+        assert_eq!(decoding_header_param(&header, "FileName").unwrap(), "naïve \"a\".pdf");
+        assert_eq!(encoding_ext_value("€ rates", "en"), "UTF-8'en'%E2%82%AC%20rates");
+
+        //a ';' inside quotes does not end the parameter
+        let params = parse_header_params("Content-Disposition: attachment; filename=\"a;b\\\".txt\"; Size=3").unwrap();
+        assert_eq!(params, [("filename".to_string(), "a;b\".txt".to_string()), ("size".to_string(), "3".to_string())]);
+        assert_eq!(decoding_header_param("attachment; filename=plain.txt", "filename").unwrap(), "plain.txt");
+
+        let latin = decoding_ext_value("iso-8859-1'en'%A3%20rates").unwrap();
+        assert_eq!(latin, ("iso-8859-1".to_string(), "en".to_string(), "£ rates".to_string()));
+    }
+
+    #[test]
+    fn testheaderparamerrors() {
+        assert!(matches!(decoding_ext_value("no-quotes"), Err(UrlencodeError::InvalidExtValue(_))));
+        assert!(matches!(decoding_ext_value("UTF-8''a%ZZ"), Err(UrlencodeError::InvalidCharacter { offset: 9, .. })));
+        assert!(matches!(decoding_ext_value("KOI8-R''%C1"), Err(UrlencodeError::UnsupportedCharset(_))));
+        assert!(matches!(decoding_header_param("attachment; name=x", "filename"), Err(UrlencodeError::MissingParameter(_))));
+        assert!(matches!(parse_header_params("a; filename*=bad"), Err(UrlencodeError::InvalidExtValue(_))));
+    }
+
+    #[test]
+    fn testhex() {
+        let bytes = [0x00, 0xAB, 0x7F];
+        assert_eq!(tohex(&bytes, false, HexSeparator::None), "00ab7f");
+        assert_eq!(tohex(&bytes, true, HexSeparator::Colon), "00:AB:7F");
+        assert_eq!(tohex(&bytes, false, HexSeparator::Space), "00 ab 7f");
+        assert_eq!(tohex(&bytes, true, HexSeparator::Escape), "\\x00\\xAB\\x7F");
+        assert_eq!(tohex(&[], true, HexSeparator::Colon), "");
+        assert_eq!("\\x".parse::<HexSeparator>(), Ok(HexSeparator::Escape));
+
+        for inp in ["00ab7f", "00AB7f", "00:ab:7F", " 00 ab\t7f\n", "\\x00\\xab\\x7f", "00a\r\nb7f"] {
+            assert_eq!(fromhex(inp).unwrap(), bytes, "{:?}", inp);
+        }
+        assert!(matches!(fromhex("00a"), Err(UrlencodeError::TruncatedEscape { offset: 2 })));
+        assert!(matches!(fromhex("0 a"), Err(UrlencodeError::InvalidCharacter { offset: 1, byte: b' ' })));
+        assert!(matches!(fromhex("00:g1"), Err(UrlencodeError::InvalidCharacter { offset: 3, byte: b'g' })));
+    }
+
+    #[test]
+    fn testhexdump() {
+        //same as `xxd`
+        assert_eq!(
+            hexdump(b"Hello, World!\x00\x01\xff\nxyz"),
+            "00000000: 4865 6c6c 6f2c 2057 6f72 6c64 2100 01ff  Hello, World!...\n\
+             00000010: 0a78 797a                                .xyz"
+        );
+        assert_eq!(hexdump(b"abc"), format!("00000000: 6162 63{}  abc", " ".repeat(32)));
+        assert_eq!(hexdump(b""), "");
+    }
+
+    #[test]
+    fn testbase32() {
+        for (data, standard, hex) in BASE32_VECTORS {
+            assert_eq!(tobase32(data.as_bytes(), Base32Alphabet::Standard, true), standard);
+            assert_eq!(tobase32(data.as_bytes(), Base32Alphabet::Hex, true), hex);
+            assert_eq!(frombase32(standard, Base32Alphabet::Standard).unwrap(), data.as_bytes());
+            assert_eq!(frombase32(hex, Base32Alphabet::Hex).unwrap(), data.as_bytes());
+            assert_eq!(frombase32(standard.trim_end_matches('='), Base32Alphabet::Standard).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn testhtmlentities() {
+        assert_eq!(encoding_html("<a href=\"x\">'&'</a>", HtmlContext::Text), "&lt;a href=\"x\"&gt;'&amp;'&lt;/a&gt;");
+        assert_eq!(encoding_html("\"it's\" & <", HtmlContext::Attribute), "&quot;it&#39;s&quot; &amp; <");
+        assert_eq!(decoding_html("&lt;&amp;&gt; caf&eacute; &copy;"), "<&> café ©");
+        assert_eq!(decoding_html("&#47;&#065;&#x2F;&#X2f;&#x1F600;"), "/A//😀");
+        assert_eq!(decoding_html("&unknown; & &amp"), "&unknown; & &amp");
+    }
+
+    #[test]
+    fn testhtmlmalformed() {
+        //no digits or a sign stay literal text
+        assert_eq!(decoding_html("&#; &#x; &#x+41; &#+65; &#-1; &#x4G;"), "&#; &#x; &#x+41; &#+65; &#-1; &#x4G;");
+        //NUL, surrogates, past U+10FFFF and overflow become U+FFFD, whatever the base
+        assert_eq!(decoding_html("&#0;&#xD800;&#x110000;&#1114112;"), "\u{fffd}".repeat(4));
+        assert_eq!(decoding_html("&#xFFFFFFFFF;&#99999999999;"), "\u{fffd}".repeat(2));
+    }
+
+    #[test]
+    fn testquotedprintable() {
+        //soft line breaks keep every encoded line within 76 characters
+        let long = "a".repeat(200) + "=" + &"é".repeat(40);
+        let encoded = toquotedprintable(long.as_bytes(), false);
+        assert!(encoded.split("\r\n").all(|line| line.len() <= QP_LINE));
+        assert!(encoded.split("\r\n").count() > 1);
+        assert_eq!(fromquotedprintable(&encoded).unwrap(), long.as_bytes());
+
+        //whitespace at the end of a line is escaped, or it would be stripped
+        assert_eq!(toquotedprintable(b"a \nb\t", false), "a=20\r\nb=09");
+        assert_eq!(fromquotedprintable("a=20\r\nb=09").unwrap(), b"a \r\nb\t");
+        assert_eq!(fromquotedprintable("abc   \r\ndef\t").unwrap(), b"abc\r\ndef");
+
+        //hard line breaks stay, soft ones join the lines
+        assert_eq!(
+            fromquotedprintable("Hello caf=C3=A9\r\nsecond line with a soft =\r\nbreak").unwrap(),
+            "Hello café\r\nsecond line with a soft break".as_bytes()
+        );
+        assert!(matches!(fromquotedprintable("a=4"), Err(UrlencodeError::TruncatedEscape { offset: 1 })));
+        assert!(matches!(fromquotedprintable("a\n=ZZ"), Err(UrlencodeError::InvalidCharacter { offset: 3, byte: b'Z' })));
+    }
+
+    #[test]
+    fn testquotedprintablebinary() {
+        assert_eq!(toquotedprintable(b"a\r\nb\n", true), "a=0D=0Ab=0A");
+        let data: Vec<u8> = (0..=255).chain(b"\r\n\n\r ".iter().copied()).collect();
+        let encoded = toquotedprintable(&data, true);
+        assert!(encoded.split("\r\n").all(|line| line.len() <= QP_LINE));
+        assert_eq!(fromquotedprintable(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn testmimewords() {
+        //RFC 2047 section 8
+        assert_eq!(decoding_mime_words("=?ISO-8859-1?Q?Andr=E9?= Pirard").unwrap(), "André Pirard");
+        assert_eq!(decoding_mime_words("(=?ISO-8859-1?Q?a?= =?ISO-8859-1?Q?b?=)").unwrap(), "(ab)");
+        assert_eq!(decoding_mime_words("(=?ISO-8859-1?Q?a_b?=)").unwrap(), "(a b)");
+        assert_eq!(decoding_mime_words("=?UTF-8?B?Y2Fmw6k=?=").unwrap(), "café");
+        assert!(matches!(decoding_mime_words("=?KOI8-R?Q?a?="), Err(UrlencodeError::UnsupportedCharset(_))));
+
+        //words are split at 75 characters and never inside a character
+        let text = "Grüße aus Köln, ".repeat(8);
+        let encoded = encoding_mime_words(&text);
+        assert!(encoded.split_whitespace().all(|word| word.len() <= MIME_WORD));
+        assert_eq!(decoding_mime_words(&encoded).unwrap(), text);
+    }
+
+    #[test]
+    fn testbase85() {
+        //the example of the Z85 specification (ZeroMQ RFC 32)
+        let data = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+        assert_eq!(tobase85(&data, Base85Variant::Z85), "HelloWorld");
+        assert_eq!(frombase85("HelloWorld", Base85Variant::Z85).unwrap(), data);
+
+        assert_eq!(tobase85(b"Hello World!", Base85Variant::Adobe), "<~87cURD]i,\"Ebo80~>");
+        assert_eq!(frombase85("<~87cURD]i,\"Ebo80~>", Base85Variant::Adobe).unwrap(), b"Hello World!");
+        assert_eq!(tobase85(b"Hello World!", Base85Variant::Rfc1924), "NM&qnZy;B1a%^NF");
+        assert_eq!(frombase85("NM&qnZy;B1a%^NF", Base85Variant::Rfc1924).unwrap(), b"Hello World!");
+    }
+}
 
 //ANCHOR - Testing Function
 // #[cfg(test)]
@@ -1,21 +1,39 @@
+#![allow(non_snake_case)]
 use clap::Parser;
-use URLencode::{Urlencode,Command, savefile};
+use std::io;
+use std::process;
+use URLencode::{Urlencode,Command, savefile, CodecRegistry, LineError, UrlencodeError};
 
 
+fn run(args: Urlencode) -> Result<Vec<LineError>, UrlencodeError> {
+    let codecs = CodecRegistry::default();
+    let ((readinp, processed, fileloc, errors), target) = match args.inp {
+        Command::Encode(form) => (form.linking(&codecs)?, form.target()), // Call linking() on the Command::Encode variant
+        Command::Decode(form) => (form.linking(&codecs)?, form.target()), // Call linking() on the Command::Decode variant
+        Command::Query(form) => (form.linking()?, form.target()),
+        Command::Normalize(form) => (form.linking()?, form.target()),
+        Command::Detect(form) => (form.linking()?, form.target()),
+    };
+    savefile(readinp, processed, fileloc, &errors, &target)?;
+    Ok(errors)
+}
+
 fn main() {
     let args = Urlencode::parse();
-    match args.inp {
-        Command::Encode(form) => {
-            let encoded_text = form.linking().unwrap(); // Call linking() on the Command::Encode variant
-            let readinp = form.getbefore_process().unwrap();
-            let _ = savefile(readinp, encoded_text.0, encoded_text.1);
-            // println!("Encoded text: {:?}", encoded_text);
-        },
-        Command::Decode(form) => {
-            let decoded_text = form.linking().unwrap(); // Call linking() on the Command::Decode variant
-            let readinp = form.getbefore_process().unwrap();
-            let _ = savefile(readinp, decoded_text.0, decoded_text.1);
-        
+    match run(args) {
+        Ok(errors) => {
+            for e in &errors {
+                eprintln!("Error on line {}: {}", e.line, e.error);
+            }
+            if let Some(first) = errors.first() {
+                process::exit(first.error.exit_code());
+            }
+        }
+        //the reader went away (e.g. `| head`), there is nobody left to tell
+        Err(UrlencodeError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(e.exit_code());
         }
     }
 }